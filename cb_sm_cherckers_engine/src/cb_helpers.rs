@@ -8,6 +8,9 @@ use sm_checkers_base::checkers_board::*;
 
 // From cb API (https://www.fierz.ch/cbdeveloper.php)
 pub const BOARD_SIZE: usize = 8;
+pub const WHITE: c_int = 1;
+#[allow(dead_code)]
+pub const BLACK: c_int = 2;
 
 enum CbTileState {
     Empty = 0,
@@ -58,8 +58,17 @@ pub struct Coor {
 use std::sync::{Arc, Mutex, Once};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::time::Duration;
 use sm_checkers_base::checkers_board::*;
-use sm_checkers_players::player_bot_random::*;
+use sm_checkers_base::player_colors::Color;
+use sm_checkers_players::player_actions::*;
+use sm_checkers_players::player_bot_search::*;
+use sm_checkers_base::checkers_rules::CheckersRules;
+use sm_checkers_players::player_trait::*;
+use sm_checkers_players::opening_book::*;
+use sm_checkers_players::player_book::PlayerBook;
+use sm_checkers_base::endgame_db::DbValue;
+use sm_checkers_base::endgame_probe::*;
 
 pub trait Singleton {
     fn get_instance() -> Arc<Mutex<Self>> where Self: Sized + 'static;
@@ -110,17 +119,54 @@ impl Singleton for BoardReceiver {
     }
 }
 
+// Opening book, next to the engine, built with "sm-checkers book"
+const BOOK_FILE: &str = "sm_checkers_book.json";
+// Endgame databases, next to the engine, generated with "sm-checkers endgame"
+const DB_DIR: &str = "sm_checkers_db";
+
 pub struct BoardReceiver {
     observers: Vec<Rc<RefCell<dyn GameBoardObserver>>>,
-    game_board: CheckersBoard
+    game_board: CheckersBoard,
+    threads: usize,     // Number of search threads, see "set threads"
+    book: Option<Arc<OpeningBook>>,     // Loaded at the first move
+    book_loaded: bool,
+    book_strength: BookStrength,    // See "set book"
+    db: Option<Arc<EndgameProbe>>,  // Opened at the first use
+    db_loaded: bool,
+    db_mbytes: usize,               // Cache of the databases, see "set dbmbytes"
+    all_scores: bool                // See "set allscores"
 }
 
 impl BoardReceiver {
     fn new() -> BoardReceiver {
         BoardReceiver {
             observers: Vec::new(),
-            game_board: CheckersBoard::new()
+            game_board: CheckersBoard::new(),
+            threads: 1,
+            book: None,
+            book_loaded: false,
+            book_strength: BookStrength::Good,
+            db: None,
+            db_loaded: false,
+            db_mbytes: DEFAULT_CACHE_MB,
+            all_scores: false
+        }
+    }
+
+    fn get_book(&mut self) -> Option<Arc<OpeningBook>> {
+        if !self.book_loaded {
+            self.book_loaded = true;
+            self.book = OpeningBook::load(BOOK_FILE).ok().map(Arc::new);
+        }
+        self.book.clone()
+    }
+
+    fn get_db(&mut self) -> Option<Arc<EndgameProbe>> {
+        if !self.db_loaded {
+            self.db_loaded = true;
+            self.db = EndgameProbe::open(std::path::Path::new(DB_DIR), self.db_mbytes).ok().map(Arc::new);
         }
+        self.db.clone()
     }
 }
 
@@ -168,32 +214,59 @@ pub extern "stdcall" fn getmove(
     let mut br = br.lock().unwrap();
 
 
-    // Temporary: for the moment, instantiate a random player every call
+    // Instantiate a search player every call, with the engine settings
     let mut color_player = Color::Black;
     if color == WHITE {
         color_player = Color::Red;
     }
-    let player = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM BOT PLAYER", color_player)));
-    br.register_observer(player);
+    let mut player = PlayerBotSearch::new("SEARCH BOT PLAYER", color_player);
+    player.set_threads(br.threads);
+    player.set_max_time(Duration::from_secs_f64(maxtime));
+    let db = br.get_db();
+    if let Some(db) = &db {
+        player.set_endgame_db(db.clone());
+    }
 
 
-    // Transform board to our reprensentation and notify all observers
+    // Transform board to our reprensentation, and give it to the player
     br.game_board = cb_board_2_checkers_board(board);
-    br.notify_observers();
-
-
-    // Fake move
-    //br.game_board.tiles[15] = TileState::BlackMan;
-    //br.game_board.tiles[11] = TileState::Empty;
-    let action = player.borrow().play_turn();
-    if let Some(ac_move) = ac.as_any().downcast_ref::<player_actions::ActionMove>() {
-        let movement = ac_move.to_movement();
-        CheckersRules::is_movement_valid(self, movement)?;
-        br.move_piece(movement).unwrap();
+    let state = GameState {
+        board: br.game_board.clone(),
+        next_color: color_player,
+        turn: 0,
+        draw_offer: None
+    };
+    let legal_moves = CheckersRules::get_legal_movements(&state.board, color_player);
+    // The result of the game, when the databases know the position
+    let result = match db.as_ref().and_then(|db| db.probe(&state.board, color_player)) {
+        Some(probe) => match probe.value {
+            DbValue::Draw => 0,
+            DbValue::Win => 1,
+            DbValue::Loss => 2
+        },
+        None => 3
+    };
+    let mut short_message = "Je pense...\n".to_string();
+    if br.all_scores {
+        // All the moves with their scores, instead of the search info, and the best one is played
+        let analysis = player.analyze(&state.board, color_player, legal_moves.len());
+        short_message = analysis.iter().map(|line| line.to_notation(color_player)).collect::<Vec<String>>().join("; ");
+        short_message.truncate(1023);
+        if let Some(best) = analysis.first() {
+            br.game_board.move_piece(&ActionMove::new(color_player, &best.tiles).to_movement()).unwrap();
+        }
+    }
+    else {
+        let mut player: Box<dyn Player> = match br.get_book() {
+            Some(book) => Box::new(PlayerBook::new(book, br.book_strength, Rc::new(RefCell::new(player)))),
+            None => Box::new(player)
+        };
+        if let Ok(Action::Move(ac_move)) = player.play_turn(&state, &legal_moves, Some(Duration::from_secs_f64(maxtime))) {
+            br.game_board.move_piece(&ac_move.to_movement()).unwrap();
+        }
     }
     checkers_board_2_cb_board(&(br.game_board), board);
 
-    let short_message = "Je pense...\n";
     let short_message_cstring = CString::new(short_message).expect("Failed to create reply CString");
     unsafe {
         std::ptr::write_bytes(short_reply, 0, 1024);
@@ -206,7 +279,7 @@ pub extern "stdcall" fn getmove(
 
 
 
-    return 3;
+    return result;
 }
 
 
@@ -215,6 +288,9 @@ pub extern "stdcall" fn getmove(
 pub extern "stdcall" fn enginecommand(command: *mut c_char, reply: *mut c_char) -> c_int {
     let mut command_str = unsafe { CStr::from_ptr(command).to_str().unwrap() }; // Convert C string to Rust string
     let mut response_str = "?"; // Your response message
+    let threads_reply;
+    let book_reply;
+    let db_reply;
 
     let path = "c:\\tmp\\sm_checkers_engine_cmd_log.txt";
     
@@ -243,16 +319,75 @@ pub extern "stdcall" fn enginecommand(command: *mut c_char, reply: *mut c_char)
         response_str = "21"; // American/English: 21, Italian: 22, Spanish: 24, Russian: 25, Brazilian: 26.
     }
     else  if cmd == "get book" {
-        response_str = "0"; // print the book strength in the reply. Currently, CheckerBoard supports values 0...3, meaning no book, all kinds of moves, good moves, best moves, respectively. How you want to interpret the book strength is your decision.
+        // print the book strength in the reply. Currently, CheckerBoard supports values 0...3, meaning no book, all kinds of moves, good moves, best moves, respectively. How you want to interpret the book strength is your decision.
+        book_reply = BoardReceiver::get_instance().lock().unwrap().book_strength.level().to_string();
+        response_str = book_reply.as_str();
+    }
+    else  if cmd.starts_with("set book ") {
+        match cmd["set book ".len()..].trim().parse::<u32>().ok().and_then(BookStrength::from_level) {
+            Some(strength) => {
+                BoardReceiver::get_instance().lock().unwrap().book_strength = strength;
+                response_str = "1";
+            }
+            None => {
+                response_str = "0";
+            }
+        }
     }
     else  if cmd == "get hashsize" {
         response_str = "0";
     }
+    else  if cmd == "get threads" {
+        threads_reply = BoardReceiver::get_instance().lock().unwrap().threads.to_string();
+        response_str = threads_reply.as_str();
+    }
+    else  if cmd.starts_with("set threads ") {
+        // Not part of the CheckerBoard protocol: sent with the "engine command" dialog
+        match cmd["set threads ".len()..].trim().parse::<usize>() {
+            Ok(threads) if threads > 0 => {
+                BoardReceiver::get_instance().lock().unwrap().threads = threads;
+                response_str = "1";
+            }
+            _ => {
+                response_str = "0";
+            }
+        }
+    }
     else  if cmd == "get dbmbytes" {
-        response_str = "0";
+        // Memory of the cache of the endgame databases, in MB
+        db_reply = BoardReceiver::get_instance().lock().unwrap().db_mbytes.to_string();
+        response_str = db_reply.as_str();
+    }
+    else  if cmd.starts_with("set dbmbytes ") {
+        match cmd["set dbmbytes ".len()..].trim().parse::<usize>() {
+            Ok(mbytes) if mbytes > 0 => {
+                let br = BoardReceiver::get_instance();
+                let mut br = br.lock().unwrap();
+                br.db_mbytes = mbytes;
+                if let Some(db) = &br.db {
+                    db.set_cache_size(mbytes);
+                }
+                response_str = "1";
+            }
+            _ => {
+                response_str = "0";
+            }
+        }
     }
     else  if cmd == "get allscores" {
-        response_str = "0"; // print 1 if you are in all scores mode (the engine displays a list of all moves with their scores instead of the normal search info), 0 if you are in normal mode. The all scores mode is a good tool for a human to help in analysis
+        // print 1 if you are in all scores mode (the engine displays a list of all moves with their scores instead of the normal search info), 0 if you are in normal mode. The all scores mode is a good tool for a human to help in analysis
+        response_str = if BoardReceiver::get_instance().lock().unwrap().all_scores { "1" } else { "0" };
+    }
+    else  if cmd.starts_with("set allscores ") {
+        match cmd["set allscores ".len()..].trim() {
+            "0" | "1" => {
+                BoardReceiver::get_instance().lock().unwrap().all_scores = cmd.ends_with('1');
+                response_str = "1";
+            }
+            _ => {
+                response_str = "0";
+            }
+        }
     }
     else  if cmd == "help" {
         response_str = "https://www.fierz.ch/cbdeveloper.php";
@@ -12,7 +12,6 @@ impl CheckersRules {
 
         if let Some(sh) = movement.as_any().downcast_ref::<Shift>() {
 
-            /* 
             // Determine player color
             let player_color = if (board.tiles[sh.from()] == TileState::BlackMan) || (board.tiles[sh.from()] == TileState::BlackKnight) {
                 Color::Black
@@ -20,15 +19,12 @@ impl CheckersRules {
             else {
                 Color::Red
             };
-            */
 
             // Rule: if a jump is possible, the player is not allowed to make a shift
             // For each piece of that color, verify if any jump is possible
-            for index in 0..32 {
-                if (board.tiles[index] == TileState::BlackMan) || (board.tiles[index] == TileState::BlackKnight) {
-                    if CheckersRules::get_possible_jumps(board, index).len() > 0 {
-                        return Err(("There is a possible jump.").into());
-                    }
+            for index in CheckersRules::get_player_pieces_indexes(board, player_color) {
+                if CheckersRules::get_possible_jumps(board, index).len() > 0 {
+                    return Err(("There is a possible jump.").into());
                 }
             }
 
@@ -332,6 +328,51 @@ impl CheckersRules {
         } 
     }       
 
+    // Returns every legal movement for the given player.
+    // Jumps are mandatory: if at least one jump is possible, only the jumps are returned.
+    // Multi-jumps are returned as a single Jump containing the whole path.
+    pub fn get_legal_movements(board: &CheckersBoard, player_color: Color) -> Vec<Box<dyn Movement>> {
+        let pieces = CheckersRules::get_player_pieces_indexes(board, player_color);
+
+        let mut movements: Vec<Box<dyn Movement>> = vec![];
+        for p in pieces.iter() {
+            let mut path = vec![];
+            CheckersRules::get_complete_jumps(board, *p, *p, &mut path, &mut movements);
+        }
+        if !movements.is_empty() {
+            return movements;
+        }
+
+        for p in pieces.iter() {
+            for sh in CheckersRules::get_possible_shifts(board, *p) {
+                movements.push(Box::new(sh));
+            }
+        }
+        return movements;
+    }
+
+    // Follows every jump sequence starting at index, and adds the complete ones to movements.
+    // The jumping piece is moved on a temporary board, the same way is_jump_valid does it.
+    fn get_complete_jumps(board: &CheckersBoard, start: usize, index: usize, path: &mut Vec<usize>, movements: &mut Vec<Box<dyn Movement>>) {
+        let jumps = CheckersRules::get_possible_jumps(board, index);
+        if jumps.is_empty() {
+            if !path.is_empty() {
+                movements.push(Box::new(Jump::new(start, path)));
+            }
+            return;
+        }
+        for ju in jumps.iter() {
+            let dst = ju.to[0];
+            let mut next_bc = (*board).clone();
+            next_bc.tiles[dst] = next_bc.tiles[index];
+            next_bc.tiles[index] = TileState::Empty;
+            next_bc.tiles[CheckersRules::get_eaten_tile_index(index, dst)] = TileState::Empty;
+            path.push(dst);
+            CheckersRules::get_complete_jumps(&next_bc, start, dst, path, movements);
+            path.pop();
+        }
+    }
+
     pub fn get_player_pieces_indexes(board: &CheckersBoard, player_color: Color) -> Vec<usize> {
         let mut player_pieces_indexes = Vec::new();
         for (i, tile) in board.tiles.iter().enumerate() {
@@ -1374,6 +1415,48 @@ mod tests {
         // NOTE:
         // The test above was imported from checkers_game.rs.
         // We could import all of them and test all of them but it's not worth it.
+
+        // Red cannot be blocked from shifting by a jump that only Black can do
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[9] = TileState::BlackMan;
+        board.tiles[13] = TileState::RedMan;
+        board.tiles[6] = TileState::RedMan;
+        board.tiles[30] = TileState::RedMan;
+        let boxed_sh: Box<dyn Movement> = Box::new(Shift::new(30, 26));
+        assert!(CheckersRules::is_movement_valid(&board, &boxed_sh).is_ok());
+    }
+
+    #[test]
+    fn test_get_legal_movements() {
+        let mut board = CheckersBoard::new();
+
+        // Default board: 7 shifts for each player
+        assert_eq!(CheckersRules::get_legal_movements(&board, Color::Black).len(), 7);
+        assert_eq!(CheckersRules::get_legal_movements(&board, Color::Red).len(), 7);
+
+        // Jumps are mandatory and multi-jumps are complete
+        board.tiles.fill(TileState::Empty);
+        board.tiles[0] = TileState::BlackMan;
+        board.tiles[5] = TileState::RedMan;
+        board.tiles[13] = TileState::RedKnight;
+        board.tiles[14] = TileState::RedKnight;
+        board.tiles[3] = TileState::BlackMan;
+        let movements = CheckersRules::get_legal_movements(&board, Color::Black);
+        assert_eq!(movements.len(), 2);
+        for m in movements.iter() {
+            let ju = m.as_any().downcast_ref::<Jump>().unwrap();
+            assert_eq!(ju.from(), 0);
+            assert_eq!(ju.to.len(), 2);
+            assert!(CheckersRules::is_movement_valid(&board, m).is_ok());
+        }
+        assert_eq!(movements[0].tiles(), vec![0, 9, 16]);
+        assert_eq!(movements[1].tiles(), vec![0, 9, 18]);
+
+        // No piece, no movement
+        assert!(CheckersRules::get_legal_movements(&board, Color::Red).len() > 0);
+        board.tiles.fill(TileState::Empty);
+        assert!(CheckersRules::get_legal_movements(&board, Color::Red).is_empty());
     }
     // more tests
 }
\ No newline at end of file
@@ -0,0 +1,638 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::checkers_board::*;
+use crate::checkers_rules::CheckersRules;
+use crate::player_colors::*;
+
+// Endgame databases: the value (win, loss or draw for the player to move) of
+// every position with few pieces, with a perfect play.
+//
+// The positions are grouped by material (number of men and kings of each color).
+// Each material has a perfect index: every index from 0 to size-1 is one position,
+// and every position has one index. The black men are ranked first (as a combination
+// of the tiles 0..27, where a black man can be), then the red men among the
+// tiles 4..31 left free, then the black kings and the red kings among the tiles left.
+//
+// A material is solved by retrograde analysis, when the materials it can reach
+// by a capture or a promotion are solved: the won and lost positions are known
+// first, and their values are propagated back to the positions leading to them
+// with un-moves. The positions never reached are draws.
+//
+// A file per material, 2 bits per position: a header then the values of the
+// positions with Black to move, then with Red to move.
+
+pub const DB_VERSION: u8 = 1;
+const MAGIC: &[u8; 4] = b"SMDB";
+pub(crate) const HEADER_SIZE: usize = 17;
+pub(crate) const DB_EXTENSION: &str = "smdb";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DbValue {
+    Draw,
+    Win,    // For the player to move
+    Loss
+}
+
+// The values of the files, as CheckerBoard numbers them
+const DRAW: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const UNKNOWN: u8 = 3;      // While solving
+
+// The value of the i-th position of packed values
+pub(crate) fn unpack_value(values: &[u8], i: usize) -> DbValue {
+    match (values[i / 4] >> ((i % 4) * 2)) & 3 {
+        WIN => DbValue::Win,
+        LOSS => DbValue::Loss,
+        _ => DbValue::Draw
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Material {
+    pub black_men: usize,
+    pub black_kings: usize,
+    pub red_men: usize,
+    pub red_kings: usize
+}
+
+impl Material {
+    pub fn new(black_men: usize, black_kings: usize, red_men: usize, red_kings: usize) -> Self {
+        Material { black_men, black_kings, red_men, red_kings }
+    }
+
+    pub fn of_board(board: &CheckersBoard) -> Self {
+        let count = |state: TileState| board.tiles.iter().filter(|t| **t == state).count();
+        Material::new(count(TileState::BlackMan), count(TileState::BlackKnight), count(TileState::RedMan), count(TileState::RedKnight))
+    }
+
+    pub fn pieces(&self) -> usize {
+        self.black_men + self.black_kings + self.red_men + self.red_kings
+    }
+
+    pub fn black_pieces(&self) -> usize {
+        self.black_men + self.black_kings
+    }
+
+    pub fn red_pieces(&self) -> usize {
+        self.red_men + self.red_kings
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("db_{}{}{}{}.{}", self.black_men, self.black_kings, self.red_men, self.red_kings, DB_EXTENSION)
+    }
+
+    // Every material with 2 to max_pieces pieces, at least one of each color,
+    // in an order where a material comes after the materials it can reach
+    // (fewer pieces after a capture, fewer men after a promotion)
+    pub fn all_up_to(max_pieces: usize) -> Vec<Material> {
+        let mut materials = vec![];
+        for pieces in 2..=max_pieces {
+            for black_men in 0..=pieces {
+                for black_kings in 0..=pieces - black_men {
+                    for red_men in 0..=pieces - black_men - black_kings {
+                        let red_kings = pieces - black_men - black_kings - red_men;
+                        let material = Material::new(black_men, black_kings, red_men, red_kings);
+                        if material.black_pieces() > 0 && material.red_pieces() > 0 && black_men <= 12 && red_men <= 12 {
+                            materials.push(material);
+                        }
+                    }
+                }
+            }
+        }
+        materials.sort_by_key(|m| (m.pieces(), m.black_men + m.red_men));
+        materials
+    }
+}
+
+const fn binomial_table() -> [[u64; 33]; 33] {
+    let mut table = [[0u64; 33]; 33];
+    let mut n = 0;
+    while n < 33 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+const BINOMIALS: [[u64; 33]; 33] = binomial_table();
+
+fn binomial(n: usize, k: usize) -> u64 {
+    if k > n { 0 } else { BINOMIALS[n][k] }
+}
+
+// Calls f with the positions of the combination of k elements of a rank, from the last one
+fn unrank_combination(mut rank: u64, k: usize, mut f: impl FnMut(usize)) {
+    let mut p = 32;
+    for i in (1..=k).rev() {
+        while binomial(p, i) > rank {
+            p -= 1;
+        }
+        rank -= binomial(p, i);
+        f(p);
+    }
+}
+
+// Perfect index of the positions of a material, for one color to move
+#[derive(Debug, Clone)]
+pub struct MaterialIndex {
+    material: Material,
+    kings_size: u64,                // Number of placements of the kings
+    red_kings_size: u64,
+    black_men_offsets: Vec<u64>,    // First index of each combination of black men
+    size: u64
+}
+
+impl MaterialIndex {
+    pub fn new(material: Material) -> Self {
+        let free = 32 - material.black_men - material.red_men;
+        let red_kings_size = binomial(free - material.black_kings, material.red_kings);
+        let kings_size = binomial(free, material.black_kings) * red_kings_size;
+        let mut black_men_offsets = vec![];
+        let mut size = 0;
+        for rank in 0..binomial(28, material.black_men) {
+            black_men_offsets.push(size);
+            let mut red_men_squares = 28;
+            unrank_combination(rank, material.black_men, |t| if t >= 4 { red_men_squares -= 1 });
+            size += binomial(red_men_squares, material.red_men) * kings_size;
+        }
+        MaterialIndex { material, kings_size, red_kings_size, black_men_offsets, size }
+    }
+
+    pub fn get_material(&self) -> Material {
+        self.material
+    }
+
+    // Number of positions, for one color to move
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // The board must have the material of the index
+    pub fn index(&self, board: &CheckersBoard) -> u64 {
+        // The ranks of each combination, and the number of pieces counted for each one
+        let (mut black_men, mut red_men, mut black_kings, mut red_kings) = (0, 0, 0, 0);
+        let (mut black_men_rank, mut red_men_rank, mut black_kings_rank, mut red_kings_rank) = (0, 0, 0, 0);
+        let mut black_men_above_3 = 0;
+        for (t, tile) in board.tiles.iter().enumerate() {
+            match tile {
+                TileState::BlackMan => {
+                    black_men += 1;
+                    black_men_rank += binomial(t, black_men);
+                    if t >= 4 {
+                        black_men_above_3 += 1;
+                    }
+                }
+                // Among the tiles 4..31 without black men
+                TileState::RedMan => {
+                    red_men += 1;
+                    red_men_rank += binomial(t - 4 - black_men_above_3, red_men);
+                }
+                // Among the tiles without men, then without black kings
+                TileState::BlackKnight => {
+                    black_kings += 1;
+                    black_kings_rank += binomial(t - black_men - red_men, black_kings);
+                }
+                TileState::RedKnight => {
+                    red_kings += 1;
+                    red_kings_rank += binomial(t - black_men - red_men - black_kings, red_kings);
+                }
+                TileState::Empty => {}
+            }
+        }
+        self.black_men_offsets[black_men_rank as usize]
+            + red_men_rank * self.kings_size
+            + black_kings_rank * self.red_kings_size
+            + red_kings_rank
+    }
+
+    pub fn board(&self, index: u64) -> CheckersBoard {
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+
+        let black_men_rank = self.black_men_offsets.partition_point(|offset| *offset <= index) - 1;
+        let rest = index - self.black_men_offsets[black_men_rank];
+        unrank_combination(black_men_rank as u64, self.material.black_men, |t| board.tiles[t] = TileState::BlackMan);
+        // The n-th empty tile from the first one
+        let nth_empty = |board: &CheckersBoard, first: usize, n: usize| {
+            (first..32).filter(|t| board.tiles[*t] == TileState::Empty).nth(n).unwrap()
+        };
+        // The positions are given from the last one: the tiles before are still empty
+        let mut placed = vec![];
+        unrank_combination(rest / self.kings_size, self.material.red_men, |p| placed.push(nth_empty(&board, 4, p)));
+        let rest = rest % self.kings_size;
+        for t in placed.drain(..) {
+            board.tiles[t] = TileState::RedMan;
+        }
+        unrank_combination(rest / self.red_kings_size, self.material.black_kings, |p| placed.push(nth_empty(&board, 0, p)));
+        for t in placed.drain(..) {
+            board.tiles[t] = TileState::BlackKnight;
+        }
+        unrank_combination(rest % self.red_kings_size, self.material.red_kings, |p| placed.push(nth_empty(&board, 0, p)));
+        for t in placed.drain(..) {
+            board.tiles[t] = TileState::RedKnight;
+        }
+        board
+    }
+}
+
+// The values of the positions of a material, 2 bits each
+#[derive(Debug, Clone)]
+pub struct WldTable {
+    index: MaterialIndex,
+    values: Vec<u8>
+}
+
+fn side_offset(index: &MaterialIndex, color: Color) -> u64 {
+    if color == Color::Black { 0 } else { index.size() }
+}
+
+// Index of the value of a position in a file, after the header
+pub(crate) fn position_index(index: &MaterialIndex, board: &CheckersBoard, color: Color) -> usize {
+    (side_offset(index, color) + index.index(board)) as usize
+}
+
+// The header of a file of file_size bytes
+pub(crate) fn read_header(header: &[u8], file_size: u64, path: &Path) -> Result<MaterialIndex, String> {
+    if header.len() < HEADER_SIZE || &header[0..4] != MAGIC {
+        return Err(format!("{} is not an endgame database", path.display()));
+    }
+    if header[4] != DB_VERSION {
+        return Err(format!("{} is a database of version {}, expected version {}", path.display(), header[4], DB_VERSION));
+    }
+    let material = Material::new(header[5] as usize, header[6] as usize, header[7] as usize, header[8] as usize);
+    let index = MaterialIndex::new(material);
+    let size = u64::from_le_bytes(header[9..17].try_into().unwrap());
+    if size != index.size() || file_size - HEADER_SIZE as u64 != (2 * size).div_ceil(4) {
+        return Err(format!("{} is truncated or corrupted", path.display()));
+    }
+    Ok(index)
+}
+
+// The value of the positions without pieces of a color
+pub(crate) fn value_without_pieces(material: &Material, color: Color) -> Option<DbValue> {
+    let (own, other) = if color == Color::Black {
+        (material.black_pieces(), material.red_pieces())
+    } else {
+        (material.red_pieces(), material.black_pieces())
+    };
+    if own == 0 {
+        Some(DbValue::Loss)
+    } else if other == 0 {
+        Some(DbValue::Win)
+    } else {
+        None
+    }
+}
+
+impl WldTable {
+    fn from_values(index: MaterialIndex, values: &[u8]) -> Self {
+        let mut packed = vec![0u8; values.len().div_ceil(4)];
+        for (i, value) in values.iter().enumerate() {
+            packed[i / 4] |= (value & 3) << ((i % 4) * 2);
+        }
+        WldTable { index, values: packed }
+    }
+
+    pub fn get_index(&self) -> &MaterialIndex {
+        &self.index
+    }
+
+    pub fn get(&self, board: &CheckersBoard, color: Color) -> DbValue {
+        unpack_value(&self.values, position_index(&self.index, board, color))
+    }
+
+    // Size of the values, in bytes
+    pub fn bytes(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let material = self.index.get_material();
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.values.len());
+        data.extend_from_slice(MAGIC);
+        data.push(DB_VERSION);
+        for count in [material.black_men, material.black_kings, material.red_men, material.red_kings] {
+            data.push(count as u8);
+        }
+        data.extend_from_slice(&self.index.size().to_le_bytes());
+        data.extend_from_slice(&self.values);
+        fs::write(path, data).map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<WldTable, String> {
+        let data = fs::read(path).map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        let index = read_header(&data, data.len() as u64, path)?;
+        Ok(WldTable { index, values: data[HEADER_SIZE..].to_vec() })
+    }
+}
+
+// The solved materials
+#[derive(Default)]
+pub struct EndgameDb {
+    tables: HashMap<Material, WldTable>
+}
+
+impl EndgameDb {
+    pub fn new() -> Self {
+        EndgameDb { tables: HashMap::new() }
+    }
+
+    // Loads every database file of a directory
+    pub fn load_dir(dir: &Path) -> Result<EndgameDb, String> {
+        let mut db = EndgameDb::new();
+        let entries = fs::read_dir(dir).map_err(|e| format!("Unable to read {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == DB_EXTENSION) {
+                let table = WldTable::load(&path)?;
+                db.tables.insert(table.index.get_material(), table);
+            }
+        }
+        Ok(db)
+    }
+
+    pub fn insert(&mut self, table: WldTable) {
+        self.tables.insert(table.index.get_material(), table);
+    }
+
+    pub fn get_table(&self, material: &Material) -> Option<&WldTable> {
+        self.tables.get(material)
+    }
+
+    pub fn get_materials(&self) -> Vec<Material> {
+        let mut materials: Vec<Material> = self.tables.keys().copied().collect();
+        materials.sort();
+        materials
+    }
+
+    // Number of pieces of the largest material
+    pub fn max_pieces(&self) -> usize {
+        self.tables.keys().map(|m| m.pieces()).max().unwrap_or(0)
+    }
+
+    // Size of the values, in bytes
+    pub fn bytes(&self) -> usize {
+        self.tables.values().map(|t| t.bytes()).sum()
+    }
+
+    // None: the material of the position is not in the database
+    pub fn lookup(&self, board: &CheckersBoard, color: Color) -> Option<DbValue> {
+        let material = Material::of_board(board);
+        if let Some(value) = value_without_pieces(&material, color) {
+            return Some(value);
+        }
+        self.tables.get(&material).map(|table| table.get(board, color))
+    }
+}
+
+// The tiles next to each tile, in the 4 directions
+fn neighbors() -> Vec<Vec<usize>> {
+    (0..32).map(|t| {
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[t] = TileState::BlackKnight;
+        CheckersRules::get_possible_shifts(&board, t).iter().map(|s| s.to).collect()
+    }).collect()
+}
+
+fn has_jump(board: &CheckersBoard, color: Color) -> bool {
+    CheckersRules::get_player_pieces_indexes(board, color).iter()
+        .any(|p| !CheckersRules::get_possible_jumps(board, *p).is_empty())
+}
+
+// Solves a material, the materials it can reach must be in db.
+// The positions of a color to move are stored after the ones of the other color.
+pub fn solve_material(material: Material, db: &EndgameDb) -> WldTable {
+    let index = MaterialIndex::new(material);
+    let size = index.size();
+    let total = 2 * size as usize;
+    let mut values = vec![UNKNOWN; total];
+    let mut counters = vec![0u8; total];     // Moves not known to lose yet
+    let mut queue: Vec<usize> = vec![];
+    let position = |color: Color, i: u64| (side_offset(&index, color) + i) as usize;
+
+    // The values known from the moves leaving the material
+    for color in [Color::Black, Color::Red] {
+        for i in 0..size {
+            let board = index.board(i);
+            let p = position(color, i);
+            let movements = CheckersRules::get_legal_movements(&board, color);
+            let mut inside = 0;
+            let mut win = false;
+            let mut draw = false;
+            for movement in movements.iter() {
+                let mut next = board.clone();
+                next.move_piece(movement).unwrap();
+                if Material::of_board(&next) == material {
+                    inside += 1;
+                    continue;
+                }
+                match db.lookup(&next, opposite_color(color)) {
+                    Some(DbValue::Loss) => win = true,
+                    Some(DbValue::Win) => {}
+                    Some(DbValue::Draw) => draw = true,
+                    None => panic!("solve_material: the material {:?} must be solved before {:?}", Material::of_board(&next), material)
+                }
+            }
+            if win {
+                values[p] = WIN;
+                queue.push(p);
+            }
+            else if inside == 0 && !draw {
+                values[p] = LOSS;
+                queue.push(p);
+            }
+            else {
+                // A draw by a move leaving the material: never a loss
+                counters[p] = inside + draw as u8;
+            }
+        }
+    }
+
+    // Propagation to the positions before, by un-moves of the player that just moved
+    let neighbors = neighbors();
+    while let Some(p) = queue.pop() {
+        let (color, i) = if (p as u64) < size { (Color::Black, p as u64) } else { (Color::Red, p as u64 - size) };
+        let previous_color = opposite_color(color);
+        let board = index.board(i);
+        for from in CheckersRules::get_player_pieces_indexes(&board, previous_color) {
+            let piece = board.tiles[from];
+            for to in neighbors[from].iter().copied() {
+                let backward = match piece {
+                    TileState::BlackMan => to < from,
+                    TileState::RedMan => to > from,
+                    _ => true
+                };
+                if !backward || board.tiles[to] != TileState::Empty {
+                    continue;
+                }
+                let mut previous = board.clone();
+                previous.tiles[to] = piece;
+                previous.tiles[from] = TileState::Empty;
+                // A jump was mandatory
+                if has_jump(&previous, previous_color) {
+                    continue;
+                }
+                let q = position(previous_color, index.index(&previous));
+                if values[q] != UNKNOWN {
+                    continue;
+                }
+                if values[p] == LOSS {
+                    values[q] = WIN;
+                    queue.push(q);
+                }
+                else {
+                    counters[q] -= 1;
+                    if counters[q] == 0 {
+                        values[q] = LOSS;
+                        queue.push(q);
+                    }
+                }
+            }
+        }
+    }
+
+    for value in values.iter_mut() {
+        if *value == UNKNOWN {
+            *value = DRAW;
+        }
+    }
+    WldTable::from_values(index, &values)
+}
+
+// Counts of the values of a table: (wins, losses, draws)
+pub fn count_values(table: &WldTable) -> (u64, u64, u64) {
+    let mut counts = (0, 0, 0);
+    for i in 0..2 * table.index.size() as usize {
+        match unpack_value(&table.values, i) {
+            DbValue::Win => counts.0 += 1,
+            DbValue::Loss => counts.1 += 1,
+            DbValue::Draw => counts.2 += 1
+        }
+    }
+    counts
+}
+
+// Generates the databases of up to max_pieces pieces in dir.
+// The files already generated are loaded instead, so that a generation can be resumed.
+// report is called after each material.
+pub fn generate(max_pieces: usize, dir: &Path, report: &mut dyn FnMut(&WldTable, bool)) -> Result<EndgameDb, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+    let mut db = EndgameDb::new();
+    for material in Material::all_up_to(max_pieces) {
+        let path: PathBuf = dir.join(material.file_name());
+        let loaded = path.exists();
+        let table = if loaded {
+            WldTable::load(&path)?
+        } else {
+            let table = solve_material(material, &db);
+            table.save(&path)?;
+            table
+        };
+        report(&table, loaded);
+        db.insert(table);
+    }
+    Ok(db)
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index() {
+        assert_eq!(MaterialIndex::new(Material::new(0, 1, 0, 1)).size(), 32 * 31);
+        // A black man on 0..3 leaves 28 tiles to the red man, 27 otherwise
+        assert_eq!(MaterialIndex::new(Material::new(1, 0, 1, 0)).size(), 4 * 28 + 24 * 27);
+
+        for material in [Material::new(1, 1, 1, 0), Material::new(2, 0, 1, 0), Material::new(0, 1, 1, 1)] {
+            let index = MaterialIndex::new(material);
+            for i in 0..index.size() {
+                let board = index.board(i);
+                assert_eq!(Material::of_board(&board), material);
+                assert!(board.tiles[28..].iter().all(|t| *t != TileState::BlackMan));
+                assert!(board.tiles[..4].iter().all(|t| *t != TileState::RedMan));
+                assert_eq!(index.index(&board), i);
+            }
+        }
+    }
+
+    fn check_values(db: &EndgameDb, material: Material) {
+        let table = db.get_table(&material).unwrap();
+        for color in [Color::Black, Color::Red] {
+            for i in 0..table.index.size() {
+                let board = table.index.board(i);
+                let next_values: Vec<DbValue> = CheckersRules::get_legal_movements(&board, color).iter().map(|m| {
+                    let mut next = board.clone();
+                    next.move_piece(m).unwrap();
+                    db.lookup(&next, opposite_color(color)).unwrap()
+                }).collect();
+                let expected = if next_values.contains(&DbValue::Loss) {
+                    DbValue::Win
+                } else if next_values.iter().all(|v| *v == DbValue::Win) {
+                    DbValue::Loss
+                } else {
+                    DbValue::Draw
+                };
+                assert_eq!(table.get(&board, color), expected, "{:?} {:?} {:?}", material, color, board.tiles);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        // The men are never more numerous after a move: enough for these materials
+        let mut db = EndgameDb::new();
+        for material in Material::all_up_to(3).into_iter().filter(|m| m.black_men + m.red_men <= 1) {
+            let table = solve_material(material, &db);
+            db.insert(table);
+        }
+        // Each value agrees with the values after one move
+        for material in [Material::new(0, 1, 0, 1), Material::new(0, 2, 0, 1), Material::new(1, 0, 0, 2), Material::new(0, 1, 1, 1)] {
+            check_values(&db, material);
+        }
+
+        let board_with = |pieces: &[(usize, TileState)]| {
+            let mut board = CheckersBoard::new();
+            board.tiles.fill(TileState::Empty);
+            for (t, state) in pieces {
+                board.tiles[*t] = *state;
+            }
+            board
+        };
+        // A king against a king, far away
+        let board = board_with(&[(0, TileState::BlackKnight), (31, TileState::RedKnight)]);
+        assert_eq!(db.lookup(&board, Color::Black), Some(DbValue::Draw));
+        // Two kings against one
+        let board = board_with(&[(13, TileState::BlackKnight), (18, TileState::BlackKnight), (31, TileState::RedKnight)]);
+        assert_eq!(db.lookup(&board, Color::Black), Some(DbValue::Win));
+        assert_eq!(db.lookup(&board, Color::Red), Some(DbValue::Loss));
+        assert_eq!(db.lookup(&CheckersBoard::new(), Color::Black), None);
+
+        // Saved and loaded
+        let dir = std::env::temp_dir().join("sm_checkers_test_endgame_db");
+        let _ = fs::remove_dir_all(&dir);
+        let mut reports = 0;
+        let generated = generate(2, &dir, &mut |_, loaded| { assert!(!loaded); reports += 1; }).unwrap();
+        assert_eq!(reports, Material::all_up_to(2).len());
+        let loaded = EndgameDb::load_dir(&dir).unwrap();
+        assert_eq!(loaded.get_materials(), generated.get_materials());
+        assert_eq!(loaded.bytes(), generated.bytes());
+        let board = board_with(&[(0, TileState::BlackKnight), (31, TileState::RedKnight)]);
+        assert_eq!(loaded.lookup(&board, Color::Red), Some(DbValue::Draw));
+        // Resumed: nothing to solve
+        generate(2, &dir, &mut |_, loaded| assert!(loaded)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
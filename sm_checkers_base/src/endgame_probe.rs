@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::checkers_board::CheckersBoard;
+use crate::endgame_db::*;
+use crate::player_colors::Color;
+
+// Probing of the endgame databases of endgame_db.rs without loading them:
+// the files stay on disk, and the blocks read are kept in a cache of limited
+// size, the least recently used block is dropped first.
+// Shared by the search threads.
+
+pub const BLOCK_SIZE: usize = 4096;
+pub const DEFAULT_CACHE_MB: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DbProbe {
+    pub value: DbValue,         // For the player to move
+    pub distance: Option<u32>   // Plies to the next capture or promotion, not in the files of version 1
+}
+
+struct DbFile {
+    index: MaterialIndex,
+    file: File,
+    size: u64       // Bytes of values, after the header
+}
+
+type BlockKey = (Material, u64);
+
+struct BlockCache {
+    max_blocks: usize,
+    blocks: HashMap<BlockKey, (Vec<u8>, u64)>,     // The data and its last use
+    uses: BTreeMap<u64, BlockKey>,                  // The blocks by last use
+    clock: u64,
+    hits: u64,
+    misses: u64
+}
+
+impl BlockCache {
+    fn new(cache_mb: usize) -> Self {
+        BlockCache {
+            max_blocks: (cache_mb * 1024 * 1024 / BLOCK_SIZE).max(1),
+            blocks: HashMap::new(),
+            uses: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0
+        }
+    }
+
+    fn get(&mut self, key: BlockKey) -> Option<&Vec<u8>> {
+        self.clock += 1;
+        let (data, last_use) = self.blocks.get_mut(&key)?;
+        self.uses.remove(last_use);
+        self.uses.insert(self.clock, key);
+        *last_use = self.clock;
+        Some(data)
+    }
+
+    fn insert(&mut self, key: BlockKey, data: Vec<u8>) {
+        while self.blocks.len() >= self.max_blocks {
+            match self.uses.pop_first() {
+                Some((_, oldest)) => self.blocks.remove(&oldest),
+                None => break
+            };
+        }
+        self.clock += 1;
+        self.uses.insert(self.clock, key);
+        self.blocks.insert(key, (data, self.clock));
+    }
+}
+
+pub struct EndgameProbe {
+    files: HashMap<Material, Mutex<DbFile>>,
+    cache: Mutex<BlockCache>,
+    max_pieces: usize
+}
+
+impl EndgameProbe {
+    // Opens every database file of a directory
+    pub fn open(dir: &Path, cache_mb: usize) -> Result<EndgameProbe, String> {
+        let mut files = HashMap::new();
+        let entries = fs::read_dir(dir).map_err(|e| format!("Unable to read {}: {}", dir.display(), e))?;
+        for path in entries.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|e| e == DB_EXTENSION)) {
+            let mut file = File::open(&path).map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+            let file_size = file.metadata().map_err(|e| format!("Unable to read {}: {}", path.display(), e))?.len();
+            let mut header = [0u8; HEADER_SIZE];
+            file.read_exact(&mut header).map_err(|_| format!("{} is not an endgame database", path.display()))?;
+            let index = read_header(&header, file_size, &path)?;
+            files.insert(index.get_material(), Mutex::new(DbFile { index, file, size: file_size - HEADER_SIZE as u64 }));
+        }
+        if files.is_empty() {
+            return Err(format!("No endgame database in {}", dir.display()));
+        }
+        let max_pieces = files.keys().map(|m| m.pieces()).max().unwrap_or(0);
+        Ok(EndgameProbe { files, cache: Mutex::new(BlockCache::new(cache_mb)), max_pieces })
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    pub fn get_materials(&self) -> Vec<Material> {
+        let mut materials: Vec<Material> = self.files.keys().copied().collect();
+        materials.sort();
+        materials
+    }
+
+    // Empties the cache
+    pub fn set_cache_size(&self, cache_mb: usize) {
+        *self.cache.lock().unwrap() = BlockCache::new(cache_mb);
+    }
+
+    pub fn get_cache_size(&self) -> usize {
+        self.cache.lock().unwrap().max_blocks * BLOCK_SIZE / (1024 * 1024)
+    }
+
+    // The blocks found in the cache, and the blocks read from the files
+    pub fn get_cache_stats(&self) -> (u64, u64) {
+        let cache = self.cache.lock().unwrap();
+        (cache.hits, cache.misses)
+    }
+
+    // None: more pieces than in the databases, or a material without file
+    pub fn probe(&self, board: &CheckersBoard, color: Color) -> Option<DbProbe> {
+        let material = Material::of_board(board);
+        if material.pieces() > self.max_pieces {
+            return None;
+        }
+        let value = match value_without_pieces(&material, color) {
+            Some(value) => value,
+            None => self.read_value(material, board, color)?
+        };
+        Some(DbProbe { value, distance: None })
+    }
+
+    fn read_value(&self, material: Material, board: &CheckersBoard, color: Color) -> Option<DbValue> {
+        let db_file = self.files.get(&material)?;
+        let position = position_index(&db_file.lock().unwrap().index, board, color);
+        let block = (position / 4 / BLOCK_SIZE) as u64;
+        let in_block = position % (4 * BLOCK_SIZE);
+
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(data) = cache.get((material, block)) {
+            let value = unpack_value(data, in_block);
+            cache.hits += 1;
+            return Some(value);
+        }
+        cache.misses += 1;
+        let data = {
+            let mut db_file = db_file.lock().unwrap();
+            let start = block * BLOCK_SIZE as u64;
+            let mut data = vec![0u8; (db_file.size - start).min(BLOCK_SIZE as u64) as usize];
+            db_file.file.seek(SeekFrom::Start(HEADER_SIZE as u64 + start)).ok()?;
+            db_file.file.read_exact(&mut data).ok()?;
+            data
+        };
+        let value = unpack_value(&data, in_block);
+        cache.insert((material, block), data);
+        Some(value)
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkers_board::TileState;
+
+    #[test]
+    fn test_probe() {
+        let dir = std::env::temp_dir().join("sm_checkers_test_endgame_probe");
+        let _ = fs::remove_dir_all(&dir);
+        assert!(EndgameProbe::open(&dir, 1).is_err());
+        let db = generate(2, &dir, &mut |_, _| {}).unwrap();
+
+        let probe = EndgameProbe::open(&dir, 1).unwrap();
+        assert_eq!(probe.max_pieces(), 2);
+        assert_eq!(probe.get_materials(), db.get_materials());
+        assert_eq!(probe.get_cache_size(), 1);
+        // Every value agrees with the loaded databases, most of them found in the cache
+        for material in db.get_materials() {
+            let index = db.get_table(&material).unwrap().get_index();
+            for i in (0..index.size()).step_by(7) {
+                let board = index.board(i);
+                for color in [Color::Black, Color::Red] {
+                    assert_eq!(probe.probe(&board, color).map(|p| p.value), db.lookup(&board, color));
+                }
+            }
+        }
+        let (hits, misses) = probe.get_cache_stats();
+        assert!(hits > 0);
+        assert!(misses >= db.get_materials().len() as u64);
+
+        // Too many pieces, or without pieces
+        assert_eq!(probe.probe(&CheckersBoard::new(), Color::Black), None);
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[12] = TileState::RedKnight;
+        assert_eq!(probe.probe(&board, Color::Black), Some(DbProbe { value: DbValue::Loss, distance: None }));
+
+        // A cache of one block
+        let mut cache = BlockCache::new(0);
+        let king_king = Material::new(0, 1, 0, 1);
+        cache.insert((king_king, 0), vec![1]);
+        cache.insert((king_king, 1), vec![2]);
+        assert!(cache.get((king_king, 0)).is_none());
+        assert_eq!(cache.get((king_king, 1)), Some(&vec![2]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
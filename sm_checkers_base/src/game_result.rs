@@ -0,0 +1,19 @@
+use crate::player_colors::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    Win(Color),
+    Draw
+}
+
+impl GameResult {
+    // Final value of the game for the given player, as in Mitchell's book:
+    // +100 for a win, -100 for a loss and 0 for a draw
+    pub fn get_value(&self, color: Color) -> f64 {
+        match self {
+            GameResult::Win(winner) if *winner == color => 100.0,
+            GameResult::Win(_) => -100.0,
+            GameResult::Draw => 0.0
+        }
+    }
+}
@@ -1,9 +1,16 @@
 pub mod checkers_board;
 pub mod checkers_rules;
+pub mod endgame_db;
+pub mod endgame_probe;
+pub mod game_result;
 pub mod movements;
+pub mod notation;
+pub mod pdn;
 pub mod player_colors;
+pub mod zobrist;
 
 pub use checkers_board::CheckersBoard;
 pub use checkers_rules::CheckersRules;
+pub use game_result::GameResult;
 pub use movements::Movement;
 pub use player_colors::Color;
\ No newline at end of file
@@ -2,6 +2,8 @@ use std::any::Any;
 
 pub trait Movement {
     fn from(&self) -> usize;
+    // All the tiles visited by the movement, starting with the source tile
+    fn tiles(&self) -> Vec<usize>;
     fn as_any(&self) -> &dyn Any;
 }
 
@@ -21,6 +23,9 @@ impl Movement for Shift {
     fn from(&self) -> usize {
         return self.from;
     }
+    fn tiles(&self) -> Vec<usize> {
+        vec![self.from, self.to]
+    }
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -46,6 +51,11 @@ impl Movement for Jump {
     fn from(&self) -> usize {
         self.from
     }
+    fn tiles(&self) -> Vec<usize> {
+        let mut tiles = vec![self.from];
+        tiles.extend(self.to.iter());
+        tiles
+    }
     fn as_any(&self) -> &dyn Any {
         self
     }
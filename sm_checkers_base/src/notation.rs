@@ -0,0 +1,183 @@
+use crate::checkers_board::*;
+use crate::movements::*;
+use crate::player_colors::Color;
+
+// Standard checkers notation: the squares are numbered from 1 to 32
+// (tile index + 1), a shift is written "11-15" and a jump "22x15x8".
+
+pub fn square_to_string(index: usize) -> String {
+    (index + 1).to_string()
+}
+
+pub fn movement_to_string(movement: &dyn Movement) -> String {
+    let separator = if movement.as_any().downcast_ref::<Jump>().is_some() { "x" } else { "-" };
+    movement.tiles().iter().map(|t| square_to_string(*t)).collect::<Vec<String>>().join(separator)
+}
+
+// "11-15", "22x15x8", "22x8" or "15": the tile indexes of the squares
+pub fn parse_squares(text: &str) -> Result<Vec<usize>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("No square given".into());
+    }
+    let mut tiles = vec![];
+    for square in text.split(['-', 'x', 'X']) {
+        let number = match square.trim().parse::<usize>() {
+            Ok(number) => number,
+            Err(_) => return Err(format!("'{}' is not a square number", square.trim()))
+        };
+        if !(1..=32).contains(&number) {
+            return Err(format!("Square {} is out of range, the squares are numbered from 1 to 32", number));
+        }
+        tiles.push(number - 1);
+    }
+    Ok(tiles)
+}
+
+// Finds the legal movement written by the text. Accepted:
+// - the complete movement: "11-15", "22x15x8"
+// - the source and the destination of a multiple jump: "22x8"
+// - the destination only, when a single movement reaches it: "15"
+// Returns the tiles of the movement.
+pub fn find_movement(text: &str, legal_movements: &[Box<dyn Movement>]) -> Result<Vec<usize>, String> {
+    let squares = parse_squares(text)?;
+    let candidates: Vec<Vec<usize>> = legal_movements.iter()
+        .map(|m| m.tiles())
+        .filter(|tiles| {
+            if squares.len() == 1 {
+                tiles.last() == squares.last()
+            }
+            else if squares.len() == 2 {
+                tiles.first() == squares.first() && tiles.last() == squares.last()
+            }
+            else {
+                *tiles == squares
+            }
+        })
+        .collect();
+
+    match candidates.len() {
+        0 => Err(format!("{} is not a legal move", text.trim())),
+        1 => Ok(candidates[0].clone()),
+        _ => Err(format!("{} is ambiguous, write the complete move", text.trim()))
+    }
+}
+
+// FEN of a position: "B:W21,22,K30:B1,2,K5"
+// The color to play, then the white (our red) and the black pieces, kings prefixed with K.
+pub fn board_to_fen(board: &CheckersBoard, next_color: Color) -> String {
+    let pieces = |man: TileState, king: TileState| {
+        board.tiles.iter().enumerate()
+            .filter_map(|(index, tile)| {
+                if *tile == man { Some(square_to_string(index)) }
+                else if *tile == king { Some(format!("K{}", square_to_string(index))) }
+                else { None }
+            })
+            .collect::<Vec<String>>().join(",")
+    };
+    let turn = if next_color == Color::Black { "B" } else { "W" };
+    format!("{}:W{}:B{}", turn, pieces(TileState::RedMan, TileState::RedKnight), pieces(TileState::BlackMan, TileState::BlackKnight))
+}
+
+fn parse_fen_color(text: &str) -> Result<Color, String> {
+    match text {
+        "B" => Ok(Color::Black),
+        "W" | "R" => Ok(Color::Red),
+        _ => Err(format!("'{}' is not a color, expected B or W", text))
+    }
+}
+
+pub fn fen_to_board(fen: &str) -> Result<(CheckersBoard, Color), String> {
+    let fen = fen.trim().trim_end_matches('.');
+    let parts: Vec<&str> = fen.split(':').collect();
+    if parts.len() != 3 {
+        return Err(format!("Invalid FEN '{}': expected <color>:<pieces>:<pieces>", fen));
+    }
+    let next_color = parse_fen_color(parts[0])?;
+    let mut board = CheckersBoard::new();
+    board.tiles.fill(TileState::Empty);
+    for part in &parts[1..] {
+        if part.is_empty() {
+            return Err(format!("Invalid FEN '{}': missing color of the pieces", fen));
+        }
+        let (color, pieces) = part.split_at(1);
+        let color = parse_fen_color(color)?;
+        for piece in pieces.split(',').filter(|p| !p.trim().is_empty()) {
+            let piece = piece.trim();
+            let (king, square) = match piece.strip_prefix('K') {
+                Some(square) => (true, square),
+                None => (false, piece)
+            };
+            let tiles = parse_squares(square)?;
+            if tiles.len() != 1 {
+                return Err(format!("Invalid FEN '{}': '{}' is not a square", fen, piece));
+            }
+            board.tiles[tiles[0]] = match (color, king) {
+                (Color::Black, false) => TileState::BlackMan,
+                (Color::Black, true) => TileState::BlackKnight,
+                (Color::Red, false) => TileState::RedMan,
+                (Color::Red, true) => TileState::RedKnight
+            };
+        }
+    }
+    Ok((board, next_color))
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notation() {
+        assert_eq!(movement_to_string(&Shift::new(10, 14)), "11-15");
+        assert_eq!(movement_to_string(&Jump::new(21, &vec![14, 7])), "22x15x8");
+
+        assert_eq!(parse_squares("11-15"), Ok(vec![10, 14]));
+        assert_eq!(parse_squares(" 22x15X8 "), Ok(vec![21, 14, 7]));
+        assert!(parse_squares("11-").is_err());
+        assert!(parse_squares("a-b").is_err());
+        assert!(parse_squares("0-4").is_err());
+        assert!(parse_squares("33").is_err());
+
+        let legal: Vec<Box<dyn Movement>> = vec![
+            Box::new(Shift::new(10, 14)),
+            Box::new(Shift::new(10, 15)),
+            Box::new(Shift::new(11, 15)),
+            Box::new(Jump::new(21, &vec![14, 7]))
+        ];
+        assert_eq!(find_movement("11-15", &legal), Ok(vec![10, 14]));
+        assert_eq!(find_movement("15", &legal), Ok(vec![10, 14]));
+        assert!(find_movement("16", &legal).is_err());
+        assert_eq!(find_movement("22x8", &legal), Ok(vec![21, 14, 7]));
+        assert_eq!(find_movement("8", &legal), Ok(vec![21, 14, 7]));
+        assert!(find_movement("22x15x9", &legal).is_err());
+        assert!(find_movement("1-5", &legal).is_err());
+    }
+
+    #[test]
+    fn test_fen() {
+        let fen = board_to_fen(&CheckersBoard::new(), Color::Black);
+        assert_eq!(fen, "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12");
+        let (board, color) = fen_to_board(&fen).unwrap();
+        assert_eq!(board.tiles, CheckersBoard::new().tiles);
+        assert_eq!(color, Color::Black);
+
+        let (board, color) = fen_to_board("W:WK3,18:B22,K32.").unwrap();
+        assert_eq!(color, Color::Red);
+        assert_eq!(board.tiles[2], TileState::RedKnight);
+        assert_eq!(board.tiles[17], TileState::RedMan);
+        assert_eq!(board.tiles[21], TileState::BlackMan);
+        assert_eq!(board.tiles[31], TileState::BlackKnight);
+        assert_eq!(board.tiles.iter().filter(|t| **t != TileState::Empty).count(), 4);
+        assert_eq!(board_to_fen(&board, color), "W:WK3,18:B22,K32");
+
+        assert!(fen_to_board("B:W21").is_err());
+        assert!(fen_to_board("X:W21:B1").is_err());
+        assert!(fen_to_board("B:W33:B1").is_err());
+        assert!(fen_to_board("B:WK:B1").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use crate::game_result::GameResult;
+use crate::player_colors::Color;
+
+// Portable Draughts Notation: the games are written as tag pairs followed by the moves.
+//
+//   [Event "Club match"]
+//   [Result "1-0"]
+//   1. 11-15 23-19 2. 8-11 22-17 {a comment} 3. 9-13 17x10 ... 1-0
+//
+// The move numbers, comments {...}, variations (...), annotations (! ?) and
+// numeric glyphs ($1) are skipped: only the moves of the main line are kept,
+// in the standard notation (see notation.rs).
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PdnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: Option<String>      // "1-0", "0-1", "1/2-1/2" or "*"
+}
+
+impl PdnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    // The result of the movetext, or of the Result tag.
+    // "1-0" is a win of the first player (Black), "2-0" and "0-2" are also used.
+    pub fn game_result(&self) -> Option<GameResult> {
+        match self.result.as_deref().or(self.tag("Result"))? {
+            "1-0" | "2-0" => Some(GameResult::Win(Color::Black)),
+            "0-1" | "0-2" => Some(GameResult::Win(Color::Red)),
+            "1/2-1/2" | "1-1" => Some(GameResult::Draw),
+            _ => None
+        }
+    }
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "2-0" | "0-2" | "1/2-1/2" | "1-1" | "0-0" | "*")
+}
+
+// "12." "12..." or "12.11-15"
+fn strip_move_number(token: &str) -> &str {
+    match token.find('.') {
+        Some(index) if token[..index].chars().all(|c| c.is_ascii_digit()) => token[index..].trim_start_matches('.'),
+        _ => token
+    }
+}
+
+// Ends the current token, a move or a result
+fn end_token(token: &mut String, game: &mut PdnGame, games: &mut Vec<PdnGame>) -> Result<(), String> {
+    let word = strip_move_number(token.trim()).trim_end_matches(['!', '?']).to_string();
+    token.clear();
+    if word.is_empty() || word.starts_with('$') {
+        return Ok(());
+    }
+    if is_result(&word) {
+        game.result = Some(word);
+        games.push(std::mem::take(game));
+    }
+    else if word.chars().all(|c| c.is_ascii_digit() || c == '-' || c == 'x' || c == 'X') {
+        game.moves.push(word);
+    }
+    else {
+        return Err(format!("Invalid move '{}' in the PDN, after {} moves", word, game.moves.len()));
+    }
+    Ok(())
+}
+
+pub fn parse_pdn(text: &str) -> Result<Vec<PdnGame>, String> {
+    let mut games = vec![];
+    let mut game = PdnGame::default();
+    let mut chars = text.chars();
+    let mut token = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                end_token(&mut token, &mut game, &mut games)?;
+                // A new game starts with tags after moves without a result
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or(format!("Invalid PDN tag [{}]", tag))?;
+                game.tags.push((name.to_string(), value.trim().trim_matches('"').to_string()));
+            }
+            '{' => {
+                end_token(&mut token, &mut game, &mut games)?;
+                if !chars.by_ref().any(|c| c == '}') {
+                    return Err("Unterminated comment in the PDN".into());
+                }
+            }
+            '(' => {
+                end_token(&mut token, &mut game, &mut games)?;
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('(') => depth += 1,
+                        Some(')') => depth -= 1,
+                        Some(_) => {}
+                        None => return Err("Unterminated variation in the PDN".into())
+                    }
+                }
+            }
+            c if c.is_whitespace() => end_token(&mut token, &mut game, &mut games)?,
+            c => token.push(c)
+        }
+    }
+    end_token(&mut token, &mut game, &mut games)?;
+    if !game.moves.is_empty() || !game.tags.is_empty() {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+pub fn load_pdn_file(path: &str) -> Result<Vec<PdnGame>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_pdn(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pdn() {
+        let text = r#"
+[Event "Test"]
+[Black "Someone"]
+[Result "1-0"]
+1. 11-15 23-19 2. 8-11! 22-17 {The main line (with a comment)} 3. 9-13
+   (3. 9-14 (3. 15-18) 27-23) 17x10 $2 4. 6x15 1-0
+
+[Event "Second"]
+1.9-14 22-18 2... 5-9 *
+[Event "Unfinished"]
+1. 11-15
+"#;
+        let games = parse_pdn(text).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("event"), Some("Test"));
+        assert_eq!(games[0].tag("Black"), Some("Someone"));
+        assert_eq!(games[0].moves, vec!["11-15", "23-19", "8-11", "22-17", "9-13", "17x10", "6x15"]);
+        assert_eq!(games[0].game_result(), Some(GameResult::Win(Color::Black)));
+        assert_eq!(games[1].moves, vec!["9-14", "22-18", "5-9"]);
+        assert_eq!(games[1].game_result(), None);
+        assert_eq!(games[2].moves, vec!["11-15"]);
+        assert_eq!(games[2].result, None);
+
+        assert!(parse_pdn("1. 11-15 hello").is_err());
+        assert!(parse_pdn("1. 11-15 {no end").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::checkers_board::*;
+use crate::player_colors::Color;
+
+// Zobrist hashing of a position (board + player to move).
+//
+// The keys are generated at compile time from a fixed seed, so a hash is
+// the same from one run to the other and can be written to files.
+
+const NB_PIECE_TYPES: usize = 4;
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> [[u64; 32]; NB_PIECE_TYPES] {
+    let mut keys = [[0u64; 32]; NB_PIECE_TYPES];
+    let mut state: u64 = 0x5345_5247_454D_414C;
+    let mut piece = 0;
+    while piece < NB_PIECE_TYPES {
+        let mut tile = 0;
+        while tile < 32 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys[piece][tile] = key;
+            tile += 1;
+        }
+        piece += 1;
+    }
+    keys
+}
+
+const PIECE_KEYS: [[u64; 32]; NB_PIECE_TYPES] = generate_keys();
+const RED_TO_MOVE_KEY: u64 = splitmix64(0x5245_445F_4D4F_5645).1;
+
+pub fn hash_board(board: &CheckersBoard, next_player_color: Color) -> u64 {
+    let mut hash = 0;
+    for (i, tile) in board.tiles.iter().enumerate() {
+        match tile {
+            TileState::Empty => {}
+            TileState::BlackMan => hash ^= PIECE_KEYS[0][i],
+            TileState::BlackKnight => hash ^= PIECE_KEYS[1][i],
+            TileState::RedMan => hash ^= PIECE_KEYS[2][i],
+            TileState::RedKnight => hash ^= PIECE_KEYS[3][i]
+        }
+    }
+    if next_player_color == Color::Red {
+        hash ^= RED_TO_MOVE_KEY;
+    }
+    hash
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_board() {
+        let mut board = CheckersBoard::new();
+        let h_black = hash_board(&board, Color::Black);
+        let h_red = hash_board(&board, Color::Red);
+        assert_ne!(h_black, h_red);
+        assert_eq!(h_black, hash_board(&CheckersBoard::new(), Color::Black));
+
+        board.tiles[8] = TileState::Empty;
+        board.tiles[12] = TileState::BlackMan;
+        assert_ne!(h_black, hash_board(&board, Color::Black));
+        board.tiles[12] = TileState::Empty;
+        board.tiles[8] = TileState::BlackMan;
+        assert_eq!(h_black, hash_board(&board, Color::Black));
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+// Strength levels of the search player, from the weakest to the strongest.
+// A weaker level searches less deep and less long, evaluates the positions
+// with noise, and sometimes plays a random move instead of the best one.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotLevel {
+    Beginner,
+    Novice,
+    Intermediate,
+    Advanced,
+    Expert
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelSettings {
+    pub max_depth: u32,
+    pub max_time: Duration,
+    pub eval_noise: f64,            // In men: each evaluation is off by up to this value
+    pub blunder_probability: f64    // Probability of a random move
+}
+
+impl BotLevel {
+    pub fn all() -> [BotLevel; 5] {
+        [BotLevel::Beginner, BotLevel::Novice, BotLevel::Intermediate, BotLevel::Advanced, BotLevel::Expert]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotLevel::Beginner => "beginner",
+            BotLevel::Novice => "novice",
+            BotLevel::Intermediate => "intermediate",
+            BotLevel::Advanced => "advanced",
+            BotLevel::Expert => "expert"
+        }
+    }
+
+    // The name of the level, or its number from 1 (beginner) to 5 (expert)
+    pub fn from_name(name: &str) -> Option<BotLevel> {
+        let name = name.trim().to_lowercase();
+        BotLevel::all().into_iter().enumerate()
+            .find(|(i, level)| level.name() == name || (i + 1).to_string() == name)
+            .map(|(_, level)| level)
+    }
+
+    pub fn settings(&self) -> LevelSettings {
+        let (max_depth, max_time_ms, eval_noise, blunder_probability) = match self {
+            BotLevel::Beginner => (2, 100, 1.0, 0.25),
+            BotLevel::Novice => (3, 200, 0.5, 0.1),
+            BotLevel::Intermediate => (5, 500, 0.25, 0.03),
+            BotLevel::Advanced => (8, 1000, 0.1, 0.0),
+            BotLevel::Expert => (20, 3000, 0.0, 0.0)
+        };
+        LevelSettings { max_depth, max_time: Duration::from_millis(max_time_ms), eval_noise, blunder_probability }
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(BotLevel::from_name("Expert"), Some(BotLevel::Expert));
+        assert_eq!(BotLevel::from_name("1"), Some(BotLevel::Beginner));
+        assert_eq!(BotLevel::from_name("6"), None);
+        assert_eq!(BotLevel::from_name("grandmaster"), None);
+        // Each level is stronger than the one before
+        for pair in BotLevel::all().windows(2) {
+            let (weaker, stronger) = (pair[0].settings(), pair[1].settings());
+            assert!(weaker.max_depth < stronger.max_depth);
+            assert!(weaker.eval_noise >= stronger.eval_noise);
+            assert!(weaker.blunder_probability >= stronger.blunder_probability);
+            assert_eq!(BotLevel::from_name(pair[0].name()), Some(pair[0]));
+        }
+    }
+}
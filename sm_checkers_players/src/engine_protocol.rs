@@ -0,0 +1,363 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use sm_checkers_base::checkers_rules::CheckersRules;
+use sm_checkers_base::movements::Movement;
+use sm_checkers_base::notation::*;
+use sm_checkers_base::player_colors::{Color, opposite_color};
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+
+// Line protocol between the game and an external engine, over its stdin/stdout.
+//
+// Game -> engine:
+//   sm-checkers 1                  Handshake with the version of the protocol, answer: "name <NAME>"
+//   seed <S>                       Seed of the engine for the game, no answer
+//   go <FEN> <TURN> <MS|none>      Play in the position, with the time left on the clock in milliseconds.
+//                                  Answer: "move 11-15" (or "move 22x15x8"), "resign" or "draw" (offer a draw)
+//   drawoffer <FEN> <TURN>         The opponent offers a draw, answer: "accept" or "decline"
+//   quit                           The game is over, the engine exits
+//
+// The engine may also write "info <TEXT>" lines at any time, they are ignored by the game.
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub enum EngineRequest {
+    Hello(u32),
+    Seed(u64),
+    Go(GameState, Option<Duration>),
+    DrawOffer(GameState),
+    Quit
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineReply {
+    Name(String),
+    Move(Vec<usize>),
+    Resign,
+    Draw,
+    Accept,
+    Decline
+}
+
+fn state_to_string(state: &GameState) -> String {
+    format!("{} {}", board_to_fen(&state.board, state.next_color), state.turn)
+}
+
+fn parse_state(fen: Option<&str>, turn: Option<&str>) -> Result<GameState, String> {
+    let (board, next_color) = fen_to_board(fen.ok_or("Missing FEN")?)?;
+    let turn = turn.ok_or("Missing turn")?.parse::<usize>().map_err(|e| format!("Invalid turn: {}", e))?;
+    Ok(GameState { board, next_color, turn, draw_offer: None })
+}
+
+pub fn format_request(request: &EngineRequest) -> String {
+    match request {
+        EngineRequest::Hello(version) => format!("sm-checkers {}", version),
+        EngineRequest::Seed(seed) => format!("seed {}", seed),
+        EngineRequest::Go(state, time_left) => {
+            let time = time_left.map_or("none".to_string(), |t| t.as_millis().to_string());
+            format!("go {} {}", state_to_string(state), time)
+        }
+        EngineRequest::DrawOffer(state) => format!("drawoffer {}", state_to_string(state)),
+        EngineRequest::Quit => "quit".to_string()
+    }
+}
+
+pub fn parse_request(line: &str) -> Result<EngineRequest, String> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("sm-checkers") => {
+            let version = words.next().and_then(|v| v.parse().ok()).ok_or("Missing protocol version")?;
+            Ok(EngineRequest::Hello(version))
+        }
+        Some("seed") => {
+            let seed = words.next().and_then(|s| s.parse().ok()).ok_or("Missing seed")?;
+            Ok(EngineRequest::Seed(seed))
+        }
+        Some("go") => {
+            let state = parse_state(words.next(), words.next())?;
+            let time_left = match words.next() {
+                Some("none") => None,
+                Some(ms) => Some(Duration::from_millis(ms.parse().map_err(|e| format!("Invalid time: {}", e))?)),
+                None => return Err("Missing time".into())
+            };
+            Ok(EngineRequest::Go(state, time_left))
+        }
+        Some("drawoffer") => {
+            let mut state = parse_state(words.next(), words.next())?;
+            state.draw_offer = Some(opposite_color(state.next_color));
+            Ok(EngineRequest::DrawOffer(state))
+        }
+        Some("quit") => Ok(EngineRequest::Quit),
+        _ => Err(format!("Unknown request: {}", line.trim()))
+    }
+}
+
+pub fn format_reply(reply: &EngineReply) -> String {
+    match reply {
+        EngineReply::Name(name) => format!("name {}", name),
+        EngineReply::Move(tiles) => format!("move {}", movement_to_string(ActionMove::new(Color::Black, tiles).to_movement().as_ref())),
+        EngineReply::Resign => "resign".to_string(),
+        EngineReply::Draw => "draw".to_string(),
+        EngineReply::Accept => "accept".to_string(),
+        EngineReply::Decline => "decline".to_string()
+    }
+}
+
+// The squares of a move are checked by the game against the legal moves
+pub fn parse_reply(line: &str) -> Result<EngineReply, String> {
+    let line = line.trim();
+    match line.split_once(' ').unwrap_or((line, "")) {
+        ("name", name) => Ok(EngineReply::Name(name.trim().to_string())),
+        ("move", text) => Ok(EngineReply::Move(parse_squares(text)?)),
+        ("resign", "") => Ok(EngineReply::Resign),
+        ("draw", "") => Ok(EngineReply::Draw),
+        ("accept", "") => Ok(EngineReply::Accept),
+        ("decline", "") => Ok(EngineReply::Decline),
+        _ => Err(format!("Unknown reply: {}", line))
+    }
+}
+
+fn write_line(output: &mut dyn Write, line: &str) -> Result<(), String> {
+    writeln!(output, "{}", line).and_then(|_| output.flush()).map_err(|e| format!("Failed to write: {}", e))
+}
+
+// The player of the engine for the color, created at its first move
+fn player_for<'a>(players: &'a mut Vec<Box<dyn Player>>, new_player: &mut dyn FnMut(Color) -> Box<dyn Player>,
+                  color: Color, seed: Option<u64>) -> &'a mut Box<dyn Player> {
+    match players.iter().position(|p| p.get_color() == color) {
+        Some(index) => &mut players[index],
+        None => {
+            let mut player = new_player(color);
+            if let Some(seed) = seed {
+                player.set_seed(seed);
+            }
+            players.push(player);
+            players.last_mut().unwrap()
+        }
+    }
+}
+
+// Game side of the protocol, over the pipes of a process or a socket
+pub struct EngineConnection {
+    name: String,       // Of the player, for the errors
+    writer: Box<dyn Write + Send>,
+    replies: Receiver<String>
+}
+
+impl EngineConnection {
+    pub fn new(name: &str, reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> Self {
+        // The lines of the engine are read by a thread, so that the game can stop waiting for them
+        let (sender, replies) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() { break; },
+                    Err(_) => break
+                }
+            }
+        });
+        EngineConnection { name: name.to_owned(), writer, replies }
+    }
+
+    // Checks that the other side speaks the protocol, returns its name
+    pub fn handshake(&mut self, timeout: Duration) -> Result<String, String> {
+        self.send(&EngineRequest::Hello(PROTOCOL_VERSION))?;
+        match self.receive(timeout)? {
+            EngineReply::Name(name) => Ok(name),
+            reply => Err(format!("{} - Unexpected answer to the handshake: {:?}", self.name, reply))
+        }
+    }
+
+    pub fn send(&mut self, request: &EngineRequest) -> Result<(), String> {
+        writeln!(self.writer, "{}", format_request(request))
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| format!("{} - Failed to send: {}", self.name, e))
+    }
+
+    // Next reply, skipping the "info" lines
+    pub fn receive(&mut self, timeout: Duration) -> Result<EngineReply, String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.replies.recv_timeout(remaining) {
+                Ok(line) => {
+                    if line.trim().is_empty() || line.starts_with("info") {
+                        continue;
+                    }
+                    return parse_reply(&line).map_err(|e| format!("{} - {}", self.name, e));
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(format!("{} - No answer in time", self.name)),
+                Err(RecvTimeoutError::Disconnected) => return Err(format!("{} - Disconnected", self.name))
+            }
+        }
+    }
+
+    // Asks for a move, checked against the legal moves.
+    // timeout: the longest wait for the answer
+    pub fn play_turn(&mut self, color: Color, state: &GameState, legal_moves: &[Box<dyn Movement>],
+                     time_left: Option<Duration>, timeout: Duration) -> Result<Action, String> {
+        self.send(&EngineRequest::Go(state.clone(), time_left))?;
+        match self.receive(timeout)? {
+            EngineReply::Move(tiles) => {
+                let text = tiles.iter().map(|t| square_to_string(*t)).collect::<Vec<String>>().join("x");
+                let tiles = find_movement(&text, legal_moves).map_err(|e| format!("{} - {}", self.name, e))?;
+                Ok(Action::Move(ActionMove::new(color, &tiles)))
+            }
+            EngineReply::Resign => Ok(Action::Resign),
+            EngineReply::Draw => Ok(Action::OfferDraw),
+            reply => Err(format!("{} - Unexpected reply to a move request: {:?}", self.name, reply))
+        }
+    }
+
+    pub fn answer_draw_offer(&mut self, state: &GameState, timeout: Duration) -> Action {
+        if self.send(&EngineRequest::DrawOffer(state.clone())).is_err() {
+            return Action::DeclineDraw;
+        }
+        match self.receive(timeout) {
+            Ok(EngineReply::Accept) => Action::AcceptDraw,
+            _ => Action::DeclineDraw
+        }
+    }
+}
+
+// Engine side of the protocol: answers the requests read from input with the players
+// created by new_player, one for each color. Returns at "quit" or at the end of the input.
+pub fn serve_engine(name: &str, input: &mut dyn BufRead, output: &mut dyn Write,
+                    new_player: &mut dyn FnMut(Color) -> Box<dyn Player>) -> Result<(), String> {
+    let mut players: Vec<Box<dyn Player>> = vec![];
+    let mut seed = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line).map_err(|e| format!("Failed to read: {}", e))? == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match parse_request(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                write_line(output, &format!("info {}", e))?;
+                continue;
+            }
+        };
+        match request {
+            EngineRequest::Hello(version) => {
+                if version != PROTOCOL_VERSION {
+                    write_line(output, &format!("info protocol version {} expected, got {}", PROTOCOL_VERSION, version))?;
+                }
+                write_line(output, &format_reply(&EngineReply::Name(name.to_string())))?;
+            }
+            EngineRequest::Seed(s) => {
+                seed = Some(s);
+                for player in players.iter_mut() {
+                    player.set_seed(s);
+                }
+            }
+            EngineRequest::Go(state, time_left) => {
+                let legal_moves: Vec<Box<dyn Movement>> = CheckersRules::get_legal_movements(&state.board, state.next_color);
+                let reply = if legal_moves.is_empty() {
+                    EngineReply::Resign
+                } else {
+                    match player_for(&mut players, new_player, state.next_color, seed).play_turn(&state, &legal_moves, time_left) {
+                        Ok(Action::Move(action_move)) => EngineReply::Move(action_move.tiles),
+                        Ok(Action::OfferDraw) => EngineReply::Draw,
+                        Ok(action) => {
+                            write_line(output, &format!("info {:?} is not supported, resigning", action))?;
+                            EngineReply::Resign
+                        }
+                        Err(e) => {
+                            write_line(output, &format!("info {}", e))?;
+                            EngineReply::Resign
+                        }
+                    }
+                };
+                write_line(output, &format_reply(&reply))?;
+            }
+            EngineRequest::DrawOffer(state) => {
+                let reply = match player_for(&mut players, new_player, state.next_color, seed).answer_draw_offer(&state) {
+                    Action::AcceptDraw => EngineReply::Accept,
+                    _ => EngineReply::Decline
+                };
+                write_line(output, &format_reply(&reply))?;
+            }
+            EngineRequest::Quit => return Ok(())
+        }
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use crate::player_bot_random::PlayerBotRandom;
+
+    #[test]
+    fn test_messages() {
+        let state = GameState { board: CheckersBoard::new(), next_color: Color::Red, turn: 3, draw_offer: None };
+        let line = format_request(&EngineRequest::Go(state.clone(), Some(Duration::from_millis(1500))));
+        assert!(line.starts_with("go W:W21,"));
+        assert!(line.ends_with(" 3 1500"));
+        match parse_request(&line) {
+            Ok(EngineRequest::Go(parsed, time_left)) => {
+                assert_eq!(parsed.board.tiles, state.board.tiles);
+                assert_eq!(parsed.next_color, Color::Red);
+                assert_eq!(parsed.turn, 3);
+                assert_eq!(time_left, Some(Duration::from_millis(1500)));
+            }
+            other => panic!("Unexpected request {:?}", other)
+        }
+        assert!(parse_request("go B:W21:B1 0").is_err());
+        assert!(parse_request("hello").is_err());
+
+        assert_eq!(format_reply(&EngineReply::Move(vec![21, 14, 7])), "move 22x15x8");
+        assert_eq!(parse_reply("move 22x15x8\n"), Ok(EngineReply::Move(vec![21, 14, 7])));
+        assert_eq!(parse_reply("name Some Engine"), Ok(EngineReply::Name("Some Engine".into())));
+        assert_eq!(parse_reply("resign"), Ok(EngineReply::Resign));
+        assert!(parse_reply("move 40-41").is_err());
+        assert!(parse_reply("resign now").is_err());
+    }
+
+    #[test]
+    fn test_serve_engine() {
+        let state = GameState { board: CheckersBoard::new(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let requests = [
+            format_request(&EngineRequest::Hello(PROTOCOL_VERSION)),
+            "nonsense".to_string(),
+            format_request(&EngineRequest::Seed(5)),
+            format_request(&EngineRequest::Go(state.clone(), None)),
+            format_request(&EngineRequest::DrawOffer(state)),
+            format_request(&EngineRequest::Quit),
+            "seed 6".to_string()
+        ];
+        let mut input = Cursor::new(requests.join("\n"));
+        let mut output = vec![];
+        let mut new_player = |color: Color| -> Box<dyn Player> {
+            let mut player = PlayerBotRandom::new("RANDOM", color);
+            player.set_verbose(false);
+            Box::new(player)
+        };
+        serve_engine("TEST ENGINE", &mut input, &mut output, &mut new_player).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "name TEST ENGINE");
+        assert!(lines[1].starts_with("info "));
+        match parse_reply(lines[2]) {
+            Ok(EngineReply::Move(tiles)) => assert!((8..12).contains(&tiles[0])),
+            other => panic!("Unexpected reply {:?}", other)
+        }
+        assert_eq!(lines[3], "decline");
+    }
+}
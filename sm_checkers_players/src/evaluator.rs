@@ -0,0 +1,335 @@
+use sm_checkers_base::checkers_board::*;
+use sm_checkers_base::checkers_rules::*;
+use sm_checkers_base::player_colors::*;
+
+// An Evaluator gives a static value to a board, from the point of view of one player:
+// the higher the value, the better the board is for that player.
+// It is shared by the search threads, hence Send + Sync.
+pub trait Evaluator: Send + Sync {
+    fn evaluate(&self, board: &CheckersBoard, color: Color) -> f64;
+}
+
+// An Evaluator that gradient based learners (LMS, TD(lambda)) can train
+pub trait DifferentiableEvaluator: Evaluator {
+    // Partial derivatives of evaluate() with respect to each parameter
+    fn get_gradient(&self, board: &CheckersBoard, color: Color) -> Vec<f64>;
+    // Adds the delta to the parameters, in the same order as the gradient
+    fn apply_update(&mut self, delta: &[f64]);
+}
+
+
+// Material only: a man is worth 1, a knight is worth 1.3
+#[derive(Default)]
+pub struct MaterialEvaluator {}
+
+impl MaterialEvaluator {
+    pub fn new() -> MaterialEvaluator {
+        MaterialEvaluator {}
+    }
+}
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, board: &CheckersBoard, color: Color) -> f64 {
+        let mut value = 0.0;
+        for tile in board.tiles.iter() {
+            value += match tile {
+                TileState::Empty => 0.0,
+                TileState::BlackMan => 1.0,
+                TileState::BlackKnight => 1.3,
+                TileState::RedMan => -1.0,
+                TileState::RedKnight => -1.3
+            };
+        }
+        if color == Color::Black { value } else { -value }
+    }
+}
+
+
+// Mitchell's linear function: V'hat(b) = w0 + w1*x1 + ... + wn*xn
+// where the x are the board features listed in WeightType.
+// The features after PlayerJumps are differences: player's value minus opponent's value.
+pub const NB_WEIGHTS: usize = 14;
+// Increment when the features change: the saved weights of an older version are not valid anymore
+pub const FEATURE_SET_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeightType {
+    Bias,
+    PlayerPieces,
+    OpponentPieces,
+    PlayerKnights,
+    OpponentKnights,
+    OpponentJumps,  // Our number of pieces threatened by the opponnent
+    PlayerJumps,    // Number of pieces we are theatening
+    BackRankGuard,  // Men still on their own back row, guarding against promotions
+    CentreControl,  // Pieces on the 8 centre tiles
+    Mobility,       // Number of legal movements
+    Runaways,       // Men with nothing in front of them up to the promotion row
+    Tempo,          // Sum of the rows advanced by the men
+    ExposedPieces,  // Pieces that can be jumped right now
+    KingCentralisation  // Sum of the closeness of the knights to the centre (0 to 6 each)
+}
+
+pub const FEATURE_NAMES: [&str; NB_WEIGHTS] = [
+    "Bias",
+    "PlayerPieces",
+    "OpponentPieces",
+    "PlayerKnights",
+    "OpponentKnights",
+    "OpponentJumps",
+    "PlayerJumps",
+    "BackRankGuard",
+    "CentreControl",
+    "Mobility",
+    "Runaways",
+    "Tempo",
+    "ExposedPieces",
+    "KingCentralisation"
+];
+
+// The 8 tiles of rows 2 to 5, columns 2 to 5
+const CENTRE_TILES: [usize; 8] = [9, 10, 13, 14, 17, 18, 21, 22];
+
+// Row (0 at the top, the black side) and column of a tile
+fn tile_row_col(index: usize) -> (i32, i32) {
+    let row = (index / 4) as i32;
+    let col = if row % 2 == 0 { 2 * (index % 4) as i32 + 1 } else { 2 * (index % 4) as i32 };
+    (row, col)
+}
+
+fn is_man(tile: TileState) -> bool {
+    tile == TileState::BlackMan || tile == TileState::RedMan
+}
+
+fn back_rank_guard(board: &CheckersBoard, color: Color) -> f64 {
+    let (back_row, man) = if color == Color::Black { (0..4, TileState::BlackMan) } else { (28..32, TileState::RedMan) };
+    back_row.filter(|i| board.tiles[*i] == man).count() as f64
+}
+
+fn centre_control(pieces: &[usize]) -> f64 {
+    pieces.iter().filter(|p| CENTRE_TILES.contains(p)).count() as f64
+}
+
+fn runaways(board: &CheckersBoard, color: Color, pieces: &[usize]) -> f64 {
+    let (direction, promotion_row) = if color == Color::Black { (1, 7) } else { (-1, 0) };
+    let mut count = 0;
+    for p in pieces.iter().filter(|p| is_man(board.tiles[**p])) {
+        let (row, col) = tile_row_col(*p);
+        // Every tile that another piece could use to intercept the man must be empty
+        let mut clear = true;
+        let mut r = row + direction;
+        let mut distance = 1;
+        while clear && (r - promotion_row) * direction <= 0 {
+            for c in (col - distance).max(0)..=(col + distance).min(7) {
+                if (r + c) % 2 == 1 && board.tiles[(r * 4 + c / 2) as usize] != TileState::Empty {
+                    clear = false;
+                }
+            }
+            r += direction;
+            distance += 1;
+        }
+        if clear {
+            count += 1;
+        }
+    }
+    count as f64
+}
+
+fn tempo(board: &CheckersBoard, color: Color, pieces: &[usize]) -> f64 {
+    let mut advance = 0;
+    for p in pieces.iter().filter(|p| is_man(board.tiles[**p])) {
+        let (row, _) = tile_row_col(*p);
+        advance += if color == Color::Black { row } else { 7 - row };
+    }
+    advance as f64
+}
+
+// Pieces of the given list that the opponent can jump over right now
+fn exposed_pieces(board: &CheckersBoard, pieces: &[usize], opp_pieces: &[usize]) -> f64 {
+    let mut exposed = vec![];
+    for opp in opp_pieces {
+        for ju in CheckersRules::get_possible_jumps(board, *opp) {
+            let eaten = CheckersRules::get_eaten_tile_index(*opp, ju.to[0]);
+            if pieces.contains(&eaten) && !exposed.contains(&eaten) {
+                exposed.push(eaten);
+            }
+        }
+    }
+    exposed.len() as f64
+}
+
+fn king_centralisation(board: &CheckersBoard, pieces: &[usize]) -> f64 {
+    let mut value = 0;
+    for p in pieces.iter().filter(|p| !is_man(board.tiles[**p])) {
+        let (row, col) = tile_row_col(*p);
+        value += (14 - (2 * row - 7).abs() - (2 * col - 7).abs()) / 2;
+    }
+    value as f64
+}
+
+#[derive(Debug, Clone)]
+pub struct WeightedFeaturesEvaluator {
+    weights: Vec<f64>
+}
+
+impl WeightedFeaturesEvaluator {
+    pub fn new(weights: &[f64]) -> WeightedFeaturesEvaluator {
+        if weights.len() != NB_WEIGHTS {
+            panic!("WeightedFeaturesEvaluator expects {} weights, received {}", NB_WEIGHTS, weights.len());
+        }
+        WeightedFeaturesEvaluator {
+            weights: weights.to_vec()
+        }
+    }
+
+    pub fn get_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn set_weights(&mut self, weights: &[f64]) {
+        if weights.len() != NB_WEIGHTS {
+            panic!("WeightedFeaturesEvaluator expects {} weights, received {}", NB_WEIGHTS, weights.len());
+        }
+        self.weights = weights.to_vec();
+    }
+
+    // The x values of the linear function, indexed by WeightType
+    pub fn get_features(board: &CheckersBoard, color: Color) -> [f64; NB_WEIGHTS] {
+        let mut features = [0.0; NB_WEIGHTS];
+        features[WeightType::Bias as usize] = 1.0;
+
+        let my_pieces = CheckersRules::get_player_pieces_indexes(board, color);
+        let mut my_knights_tile_state = TileState::BlackKnight;
+        if color == Color::Red {
+            my_knights_tile_state = TileState::RedKnight;
+        }
+        let my_knights_count = my_pieces.iter().filter(|&x| board.tiles[*x] == my_knights_tile_state).count();
+
+        let opp_pieces = CheckersRules::get_player_pieces_indexes(board, opposite_color(color));
+        let mut opp_knights_tile_state = TileState::RedKnight;
+        if color == Color::Red {
+            opp_knights_tile_state = TileState::BlackKnight;
+        }
+        let opp_knights_count = opp_pieces.iter().filter(|&x| board.tiles[*x] == opp_knights_tile_state).count();
+
+        features[WeightType::PlayerPieces as usize] = my_pieces.len() as f64;
+        features[WeightType::OpponentPieces as usize] = opp_pieces.len() as f64;
+        features[WeightType::PlayerKnights as usize] = my_knights_count as f64;
+        features[WeightType::OpponentKnights as usize] = opp_knights_count as f64;
+
+        let mut opp_jumps_count = 0;
+        for piece in &opp_pieces {
+            opp_jumps_count += CheckersRules::get_possible_jumps(board, *piece).len();
+        }
+        features[WeightType::OpponentJumps as usize] = opp_jumps_count as f64;
+
+        let mut my_jumps_count = 0;
+        for piece in &my_pieces {
+            my_jumps_count += CheckersRules::get_possible_jumps(board, *piece).len();
+        }
+        features[WeightType::PlayerJumps as usize] = my_jumps_count as f64;
+
+        let opp_color = opposite_color(color);
+        features[WeightType::BackRankGuard as usize] = back_rank_guard(board, color) - back_rank_guard(board, opp_color);
+        features[WeightType::CentreControl as usize] = centre_control(&my_pieces) - centre_control(&opp_pieces);
+        features[WeightType::Mobility as usize] = CheckersRules::get_legal_movements(board, color).len() as f64
+            - CheckersRules::get_legal_movements(board, opp_color).len() as f64;
+        features[WeightType::Runaways as usize] = runaways(board, color, &my_pieces) - runaways(board, opp_color, &opp_pieces);
+        features[WeightType::Tempo as usize] = tempo(board, color, &my_pieces) - tempo(board, opp_color, &opp_pieces);
+        features[WeightType::ExposedPieces as usize] = exposed_pieces(board, &my_pieces, &opp_pieces) - exposed_pieces(board, &opp_pieces, &my_pieces);
+        features[WeightType::KingCentralisation as usize] = king_centralisation(board, &my_pieces) - king_centralisation(board, &opp_pieces);
+        features
+    }
+}
+
+impl Evaluator for WeightedFeaturesEvaluator {
+    fn evaluate(&self, board: &CheckersBoard, color: Color) -> f64 {
+        let features = WeightedFeaturesEvaluator::get_features(board, color);
+        self.weights.iter().zip(features.iter()).map(|(w, x)| w * x).sum()
+    }
+}
+
+// The function is linear: the gradient is the features
+impl DifferentiableEvaluator for WeightedFeaturesEvaluator {
+    fn get_gradient(&self, board: &CheckersBoard, color: Color) -> Vec<f64> {
+        WeightedFeaturesEvaluator::get_features(board, color).to_vec()
+    }
+
+    fn apply_update(&mut self, delta: &[f64]) {
+        for (w, d) in self.weights.iter_mut().zip(delta.iter()) {
+            *w += d;
+        }
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_evaluator() {
+        let mut board = CheckersBoard::new();
+        let evaluator = MaterialEvaluator::new();
+        assert_eq!(evaluator.evaluate(&board, Color::Black), 0.0);
+
+        board.tiles[0] = TileState::BlackKnight;
+        board.tiles[31] = TileState::Empty;
+        assert!((evaluator.evaluate(&board, Color::Black) - 1.3).abs() < 1e-9);
+        assert!((evaluator.evaluate(&board, Color::Red) + 1.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_weighted_features_evaluator() {
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[9] = TileState::BlackMan;
+        board.tiles[13] = TileState::RedKnight;
+        board.tiles[31] = TileState::RedMan;
+
+        let features = WeightedFeaturesEvaluator::get_features(&board, Color::Black);
+        assert_eq!(features[0..7], [1.0, 1.0, 2.0, 0.0, 1.0, 1.0, 1.0]);
+        let features = WeightedFeaturesEvaluator::get_features(&board, Color::Red);
+        assert_eq!(features[0..7], [1.0, 2.0, 1.0, 1.0, 0.0, 1.0, 1.0]);
+
+        let mut weights = [0.0; NB_WEIGHTS];
+        weights[0..7].copy_from_slice(&[0.5, 1.0, -1.0, 0.5, -0.5, -0.25, 0.25]);
+        let evaluator = WeightedFeaturesEvaluator::new(&weights);
+        assert!((evaluator.evaluate(&board, Color::Black) - (0.5 + 1.0 - 2.0 - 0.5 - 0.25 + 0.25)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_positional_features() {
+        // Default board: everything is symmetrical
+        let board = CheckersBoard::new();
+        let features = WeightedFeaturesEvaluator::get_features(&board, Color::Black);
+        for (i, feature) in features.iter().enumerate().skip(WeightType::BackRankGuard as usize) {
+            assert_eq!(*feature, 0.0, "Feature {} is not 0", i);
+        }
+
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[3] = TileState::BlackMan;   // back rank, row 0
+        board.tiles[13] = TileState::BlackMan;  // centre, row 3, blocked by 17
+        board.tiles[17] = TileState::RedKnight; // centre, row 4, column 3
+        board.tiles[8] = TileState::RedMan;     // row 2, runaway
+        let features = WeightedFeaturesEvaluator::get_features(&board, Color::Black);
+        assert_eq!(features[WeightType::BackRankGuard as usize], 1.0);
+        assert_eq!(features[WeightType::CentreControl as usize], 0.0);
+        assert_eq!(features[WeightType::Runaways as usize], -1.0);
+        assert_eq!(features[WeightType::Tempo as usize], (0.0 + 3.0) - (7.0 - 2.0));
+        assert_eq!(features[WeightType::KingCentralisation as usize], -6.0);
+        // Black 13 can jump the red knight, the red knight cannot jump back
+        assert_eq!(features[WeightType::ExposedPieces as usize], -1.0);
+        // Black must jump, red has 2 shifts for the man and 3 for the knight
+        assert_eq!(features[WeightType::Mobility as usize], 1.0 - 5.0);
+
+        let features_red = WeightedFeaturesEvaluator::get_features(&board, Color::Red);
+        for i in (WeightType::BackRankGuard as usize)..NB_WEIGHTS {
+            assert_eq!(features_red[i], -features[i]);
+        }
+    }
+}
@@ -2,11 +2,35 @@ pub mod player_trait;
 pub mod player_human_console;
 pub mod player_bot_random;
 pub mod player_bot_ai;
+pub mod player_bot_search;
+pub mod bot_level;
+pub mod player_mcts;
+pub mod player_external;
+pub mod player_network;
+pub mod player_scripted;
+pub mod opening_book;
+pub mod player_book;
+pub mod transposition_table;
 pub mod player_actions;
+pub mod evaluator;
+pub mod weights_file;
+pub mod texel_tuning;
+pub mod td_lambda;
+pub mod neural_evaluator;
+pub mod engine_protocol;
 
 pub use player_trait::Player;
 pub use player_human_console::PlayerHumanConsole;
 pub use player_bot_random::PlayerBotRandom;
 pub use player_bot_ai::PlayerBotAI;
+pub use player_bot_search::PlayerBotSearch;
+pub use bot_level::BotLevel;
+pub use player_mcts::PlayerMcts;
+pub use player_external::PlayerExternal;
+pub use player_network::PlayerNetwork;
+pub use player_scripted::PlayerScripted;
+pub use player_book::PlayerBook;
+pub use texel_tuning::TexelTuner;
+pub use player_actions::Action;
 pub use player_actions::ActionMove;
-pub use player_actions::ActionQuit;
+pub use evaluator::Evaluator;
@@ -0,0 +1,267 @@
+use std::fs;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use sm_checkers_base::checkers_board::*;
+use sm_checkers_base::player_colors::*;
+
+use crate::evaluator::*;
+
+// Small multilayer perceptron, trained on the CPU.
+//
+// Input: the 32 tiles, seen from the player, as 4 planes of 32 values
+// (player's men, player's knights, opponent's men, opponent's knights).
+// The board is flipped for the red player, so that the player always moves down the board.
+// Every layer uses tanh, the output is scaled to the game values (+100 win, -100 loss).
+
+pub const MLP_INPUTS: usize = 4 * 32;
+const OUTPUT_SCALE: f64 = 100.0;
+const MLP_FILE_VERSION: u32 = 1;
+
+// The 4 x 32 inputs of the network
+pub fn encode_board(board: &CheckersBoard, color: Color) -> Vec<f64> {
+    let mut input = vec![0.0; MLP_INPUTS];
+    for i in 0..32 {
+        // Tile i as seen by the player
+        let tile = if color == Color::Black { board.tiles[i] } else { board.tiles[31 - i] };
+        let plane = match (tile, color) {
+            (TileState::Empty, _) => continue,
+            (TileState::BlackMan, Color::Black) | (TileState::RedMan, Color::Red) => 0,
+            (TileState::BlackKnight, Color::Black) | (TileState::RedKnight, Color::Red) => 1,
+            (TileState::RedMan, Color::Black) | (TileState::BlackMan, Color::Red) => 2,
+            (TileState::RedKnight, Color::Black) | (TileState::BlackKnight, Color::Red) => 3
+        };
+        input[plane * 32 + i] = 1.0;
+    }
+    input
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MlpEvaluator {
+    version: u32,
+    layer_sizes: Vec<usize>,    // Inputs, hidden layers, 1 output
+    weights: Vec<Vec<f64>>,     // weights[l][j * inputs + i]: from input i to neuron j of layer l
+    biases: Vec<Vec<f64>>
+}
+
+impl MlpEvaluator {
+    // Random initial weights (Xavier uniform), from the seed
+    pub fn new(hidden_layers: &[usize], seed: u64) -> MlpEvaluator {
+        let mut layer_sizes = vec![MLP_INPUTS];
+        layer_sizes.extend_from_slice(hidden_layers);
+        layer_sizes.push(1);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut weights = vec![];
+        let mut biases = vec![];
+        for l in 1..layer_sizes.len() {
+            let (n_in, n_out) = (layer_sizes[l - 1], layer_sizes[l]);
+            let limit = (6.0 / (n_in + n_out) as f64).sqrt();
+            weights.push((0..n_in * n_out).map(|_| rng.gen_range(-limit..limit)).collect());
+            biases.push(vec![0.0; n_out]);
+        }
+        MlpEvaluator {
+            version: MLP_FILE_VERSION,
+            layer_sizes,
+            weights,
+            biases
+        }
+    }
+
+    pub fn get_layer_sizes(&self) -> &[usize] {
+        &self.layer_sizes
+    }
+
+    pub fn get_nb_parameters(&self) -> usize {
+        self.weights.iter().map(|w| w.len()).sum::<usize>() + self.biases.iter().map(|b| b.len()).sum::<usize>()
+    }
+
+    // Outputs of every layer, starting with the input
+    fn forward(&self, input: Vec<f64>) -> Vec<Vec<f64>> {
+        let mut activations = vec![input];
+        for l in 0..self.weights.len() {
+            let previous = activations.last().unwrap();
+            let n_in = previous.len();
+            let output: Vec<f64> = (0..self.biases[l].len())
+                .map(|j| {
+                    let row = &self.weights[l][j * n_in..(j + 1) * n_in];
+                    let z: f64 = self.biases[l][j] + row.iter().zip(previous.iter()).map(|(w, x)| w * x).sum::<f64>();
+                    z.tanh()
+                })
+                .collect();
+            activations.push(output);
+        }
+        activations
+    }
+
+    // Backpropagation: derivatives of the output with respect to every parameter,
+    // in the order of apply_update: the weights then the biases of each layer
+    fn backward(&self, activations: &[Vec<f64>]) -> Vec<f64> {
+        let nb_layers = self.weights.len();
+        let mut layer_gradients: Vec<Vec<f64>> = vec![vec![]; nb_layers];
+
+        // Derivative of the output with respect to z of the current layer
+        let output = activations[nb_layers][0];
+        let mut delta = vec![OUTPUT_SCALE * (1.0 - output * output)];
+        for l in (0..nb_layers).rev() {
+            let inputs = &activations[l];
+            let n_in = inputs.len();
+            let mut gradient = Vec::with_capacity(self.weights[l].len() + delta.len());
+            for d in delta.iter() {
+                gradient.extend(inputs.iter().map(|x| d * x));
+            }
+            gradient.extend(delta.iter());
+            layer_gradients[l] = gradient;
+
+            if l > 0 {
+                delta = (0..n_in)
+                    .map(|i| {
+                        let back: f64 = delta.iter().enumerate().map(|(j, d)| d * self.weights[l][j * n_in + i]).sum();
+                        back * (1.0 - inputs[i] * inputs[i])
+                    })
+                    .collect();
+            }
+        }
+        layer_gradients.concat()
+    }
+
+    // Supervised training on labelled positions: (board, player, value for the player).
+    // Stochastic gradient descent on the squared error, one pass per epoch.
+    // Returns the mean squared error of the last epoch, before the updates.
+    pub fn train_on_examples(&mut self, examples: &[(CheckersBoard, Color, f64)], learning_rate: f64, epochs: usize) -> f64 {
+        let mut mean_squared_error = 0.0;
+        for _ in 0..epochs {
+            let mut squared_error_sum = 0.0;
+            for (board, color, target) in examples.iter() {
+                let activations = self.forward(encode_board(board, *color));
+                let value = OUTPUT_SCALE * activations.last().unwrap()[0];
+                let error = target - value;
+                squared_error_sum += error * error;
+                // The values are in game units (+-100): the step is normalized by the scale
+                let step = learning_rate * error / (OUTPUT_SCALE * OUTPUT_SCALE);
+                let delta: Vec<f64> = self.backward(&activations).iter().map(|g| step * g).collect();
+                self.apply_update(&delta);
+            }
+            mean_squared_error = if examples.is_empty() { 0.0 } else { squared_error_sum / examples.len() as f64 };
+        }
+        mean_squared_error
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| format!("Unable to serialize the network: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<MlpEvaluator, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        let mlp: MlpEvaluator = serde_json::from_str(&json).map_err(|e| format!("Invalid network file {}: {}", path, e))?;
+        if mlp.version != MLP_FILE_VERSION {
+            return Err(format!("{} has the version {}, expected version {}", path, mlp.version, MLP_FILE_VERSION));
+        }
+        let sizes_ok = mlp.layer_sizes.len() >= 2
+            && mlp.layer_sizes[0] == MLP_INPUTS
+            && *mlp.layer_sizes.last().unwrap() == 1
+            && mlp.weights.len() == mlp.layer_sizes.len() - 1
+            && mlp.biases.len() == mlp.layer_sizes.len() - 1
+            && (1..mlp.layer_sizes.len()).all(|l| {
+                mlp.weights[l - 1].len() == mlp.layer_sizes[l - 1] * mlp.layer_sizes[l] && mlp.biases[l - 1].len() == mlp.layer_sizes[l]
+            });
+        if !sizes_ok {
+            return Err(format!("{} does not describe a valid network", path));
+        }
+        Ok(mlp)
+    }
+}
+
+impl Evaluator for MlpEvaluator {
+    fn evaluate(&self, board: &CheckersBoard, color: Color) -> f64 {
+        OUTPUT_SCALE * self.forward(encode_board(board, color)).last().unwrap()[0]
+    }
+}
+
+impl DifferentiableEvaluator for MlpEvaluator {
+    fn get_gradient(&self, board: &CheckersBoard, color: Color) -> Vec<f64> {
+        self.backward(&self.forward(encode_board(board, color)))
+    }
+
+    fn apply_update(&mut self, delta: &[f64]) {
+        let mut d = delta.iter();
+        for l in 0..self.weights.len() {
+            for w in self.weights[l].iter_mut() {
+                *w += d.next().unwrap();
+            }
+            for b in self.biases[l].iter_mut() {
+                *b += d.next().unwrap();
+            }
+        }
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_board() {
+        let board = CheckersBoard::new();
+        let black = encode_board(&board, Color::Black);
+        let red = encode_board(&board, Color::Red);
+        // The starting position looks the same for both players
+        assert_eq!(black, red);
+        assert_eq!(black.iter().sum::<f64>(), 24.0);
+        assert_eq!(black[0], 1.0);
+        assert_eq!(black[2 * 32 + 31], 1.0);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let mlp = MlpEvaluator::new(&[8, 4], 1);
+        let mut board = CheckersBoard::new();
+        board.tiles[9] = TileState::Empty;
+        board.tiles[13] = TileState::BlackKnight;
+        let gradient = mlp.get_gradient(&board, Color::Black);
+        assert_eq!(gradient.len(), mlp.get_nb_parameters());
+
+        // Compare with the numerical derivatives
+        let epsilon = 1e-6;
+        for p in (0..gradient.len()).step_by(97) {
+            let mut delta = vec![0.0; gradient.len()];
+            delta[p] = epsilon;
+            let mut plus = mlp.clone();
+            plus.apply_update(&delta);
+            delta[p] = -epsilon;
+            let mut minus = mlp.clone();
+            minus.apply_update(&delta);
+            let numerical = (plus.evaluate(&board, Color::Black) - minus.evaluate(&board, Color::Black)) / (2.0 * epsilon);
+            assert!((numerical - gradient[p]).abs() < 1e-4, "Parameter {}: {} vs {}", p, numerical, gradient[p]);
+        }
+    }
+
+    #[test]
+    fn test_train_and_save() {
+        let mut mlp = MlpEvaluator::new(&[16], 2);
+        let mut good = CheckersBoard::new();
+        good.tiles[20] = TileState::Empty;
+        good.tiles[21] = TileState::Empty;
+        let mut bad = CheckersBoard::new();
+        bad.tiles[8] = TileState::Empty;
+        bad.tiles[9] = TileState::Empty;
+        let examples = vec![(good.clone(), Color::Black, 50.0), (bad.clone(), Color::Black, -50.0)];
+
+        let first_error = mlp.train_on_examples(&examples, 0.1, 1);
+        let last_error = mlp.train_on_examples(&examples, 0.1, 200);
+        assert!(last_error < first_error);
+        assert!(mlp.evaluate(&good, Color::Black) > mlp.evaluate(&bad, Color::Black));
+
+        let path = std::env::temp_dir().join("sm_checkers_test_mlp.json");
+        let path = path.to_str().unwrap();
+        mlp.save(path).unwrap();
+        assert_eq!(MlpEvaluator::load(path).unwrap(), mlp);
+        std::fs::remove_file(path).unwrap();
+    }
+}
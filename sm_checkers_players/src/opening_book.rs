@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fs;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sm_checkers_base::checkers_board::CheckersBoard;
+use sm_checkers_base::checkers_rules::CheckersRules;
+use sm_checkers_base::game_result::GameResult;
+use sm_checkers_base::notation::find_movement;
+use sm_checkers_base::pdn::PdnGame;
+use sm_checkers_base::player_colors::*;
+use sm_checkers_base::zobrist::hash_board;
+
+// Opening book: the moves played in a collection of games, for each position
+// (zobrist hash of the board and of the color to play) of their first plies.
+// Each move keeps the number of games where it was played, and the points
+// scored with it by the player who played it (win: 1, draw: 0.5, loss: 0).
+// Saved as JSON.
+
+pub const BOOK_VERSION: u32 = 1;
+
+// The book strength of CheckerBoard: which moves of the book are played
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookStrength {
+    Off,        // No book
+    All,        // All the moves, the most played ones more often
+    Good,       // The moves scoring at least half of the points
+    Best        // The move with the best score only
+}
+
+impl BookStrength {
+    pub fn from_level(level: u32) -> Option<BookStrength> {
+        match level {
+            0 => Some(BookStrength::Off),
+            1 => Some(BookStrength::All),
+            2 => Some(BookStrength::Good),
+            3 => Some(BookStrength::Best),
+            _ => None
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        match self {
+            BookStrength::Off => 0,
+            BookStrength::All => 1,
+            BookStrength::Good => 2,
+            BookStrength::Best => 3
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookMove {
+    pub tiles: Vec<usize>,
+    pub games: u32,
+    pub results: u32,   // Games with a known result
+    pub points: f64     // Scored in these games
+}
+
+impl BookMove {
+    // Expected score of the move, 0.5 without any known result
+    pub fn score(&self) -> f64 {
+        if self.results == 0 { 0.5 } else { self.points / self.results as f64 }
+    }
+}
+
+// The best score, then the most played
+fn best_move<'a>(moves: &[&'a BookMove]) -> Option<&'a BookMove> {
+    moves.iter()
+        .max_by(|a, b| a.score().partial_cmp(&b.score()).unwrap().then(a.games.cmp(&b.games)))
+        .copied()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpeningBook {
+    pub version: u32,
+    pub max_plies: usize,
+    pub positions: HashMap<u64, Vec<BookMove>>
+}
+
+impl OpeningBook {
+    pub fn new(max_plies: usize) -> Self {
+        OpeningBook { version: BOOK_VERSION, max_plies, positions: HashMap::new() }
+    }
+
+    // Adds the first max_plies moves of a game, played from the starting position.
+    // Returns the number of moves added, Err if a move is illegal (the moves before are kept).
+    pub fn add_game(&mut self, game: &PdnGame) -> Result<usize, String> {
+        if game.tag("FEN").is_some() {
+            return Err("The game does not start from the starting position".into());
+        }
+        let result = game.game_result();
+        let mut board = CheckersBoard::new();
+        let mut color = Color::Black;
+        for (ply, text) in game.moves.iter().take(self.max_plies).enumerate() {
+            let legal_moves = CheckersRules::get_legal_movements(&board, color);
+            let tiles = find_movement(text, &legal_moves).map_err(|e| format!("Move {}: {}", ply + 1, e))?;
+            let moves = self.positions.entry(hash_board(&board, color)).or_default();
+            let index = match moves.iter().position(|m| m.tiles == tiles) {
+                Some(index) => index,
+                None => {
+                    moves.push(BookMove { tiles: tiles.clone(), games: 0, results: 0, points: 0.0 });
+                    moves.len() - 1
+                }
+            };
+            let book_move = &mut moves[index];
+            book_move.games += 1;
+            if let Some(result) = result {
+                book_move.results += 1;
+                book_move.points += match result {
+                    GameResult::Win(winner) if winner == color => 1.0,
+                    GameResult::Win(_) => 0.0,
+                    GameResult::Draw => 0.5
+                };
+            }
+
+            let movement = legal_moves.iter().find(|m| m.tiles() == tiles).unwrap();
+            board.move_piece(movement)?;
+            color = opposite_color(color);
+        }
+        Ok(game.moves.len().min(self.max_plies))
+    }
+
+    // Returns the number of games added, and the errors of the others
+    pub fn add_games(&mut self, games: &[PdnGame]) -> (usize, Vec<String>) {
+        let mut added = 0;
+        let mut errors = vec![];
+        for (index, game) in games.iter().enumerate() {
+            match self.add_game(game) {
+                Ok(_) => added += 1,
+                Err(e) => errors.push(format!("Game {}: {}", index + 1, e))
+            }
+        }
+        (added, errors)
+    }
+
+    // The moves of the book for the position, selected by the strength.
+    // The moves that are not legal are ignored, in case of a collision of the hashes.
+    pub fn candidates(&self, board: &CheckersBoard, color: Color, strength: BookStrength) -> Vec<&BookMove> {
+        let legal_moves = CheckersRules::get_legal_movements(board, color);
+        let mut moves: Vec<&BookMove> = match self.positions.get(&hash_board(board, color)) {
+            Some(moves) => moves.iter().filter(|m| legal_moves.iter().any(|l| l.tiles() == m.tiles)).collect(),
+            None => return vec![]
+        };
+        match strength {
+            BookStrength::Off => vec![],
+            BookStrength::All => moves,
+            BookStrength::Good => {
+                let best = best_move(&moves);
+                moves.retain(|m| m.score() >= 0.5);
+                if moves.is_empty() { best.into_iter().collect() } else { moves }
+            }
+            BookStrength::Best => best_move(&moves).into_iter().collect()
+        }
+    }
+
+    // A move of the book, drawn with a probability proportional to its number of games
+    pub fn choose_move<R: Rng>(&self, board: &CheckersBoard, color: Color, strength: BookStrength, rng: &mut R) -> Option<Vec<usize>> {
+        let candidates = self.candidates(board, color, strength);
+        let total: u32 = candidates.iter().map(|m| m.games).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut draw = rng.gen_range(0..total);
+        for candidate in candidates {
+            if draw < candidate.games {
+                return Some(candidate.tiles.clone());
+            }
+            draw -= candidate.games;
+        }
+        None
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| format!("Unable to serialize the book: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<OpeningBook, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        let book: OpeningBook = serde_json::from_str(&json).map_err(|e| format!("Invalid book {}: {}", path, e))?;
+        if book.version != BOOK_VERSION {
+            return Err(format!("{} is a book of version {}, expected version {}", path, book.version, BOOK_VERSION));
+        }
+        Ok(book)
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use sm_checkers_base::pdn::parse_pdn;
+
+    fn test_book() -> OpeningBook {
+        let games = parse_pdn(r#"
+[Result "1-0"] 1. 11-15 23-19 2. 8-11 22-17 1-0
+[Result "1-0"] 1. 11-15 23-19 2. 9-13 1-0
+[Result "0-1"] 1. 11-15 22-18 0-1
+[Result "0-1"] 1. 9-13 22-18 0-1
+[Result "1/2-1/2"] 1. 10-14 1/2-1/2
+1. 11-15 24-28 *
+"#).unwrap();
+        let mut book = OpeningBook::new(3);
+        let (added, errors) = book.add_games(&games);
+        assert_eq!(added, 5);
+        assert_eq!(errors.len(), 1);
+        book
+    }
+
+    #[test]
+    fn test_book_candidates() {
+        let book = test_book();
+        let board = CheckersBoard::new();
+        let all = book.candidates(&board, Color::Black, BookStrength::All);
+        assert_eq!(all.len(), 3);
+        let move_11_15 = all.iter().find(|m| m.tiles == vec![10, 14]).unwrap();
+        // The illegal game still counts its moves before the illegal one
+        assert_eq!(move_11_15.games, 4);
+        assert_eq!(move_11_15.results, 3);
+        assert!((move_11_15.score() - 2.0 / 3.0).abs() < 1e-9);
+
+        let good: Vec<Vec<usize>> = book.candidates(&board, Color::Black, BookStrength::Good).iter().map(|m| m.tiles.clone()).collect();
+        assert_eq!(good, vec![vec![10, 14], vec![9, 13]]);
+        let best: Vec<Vec<usize>> = book.candidates(&board, Color::Black, BookStrength::Best).iter().map(|m| m.tiles.clone()).collect();
+        assert_eq!(best, vec![vec![10, 14]]);
+        assert!(book.candidates(&board, Color::Black, BookStrength::Off).is_empty());
+        assert!(book.candidates(&board, Color::Red, BookStrength::All).is_empty());
+
+        // Only 3 plies
+        assert_eq!(book.positions.len(), 4);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let tiles = book.choose_move(&board, Color::Black, BookStrength::All, &mut rng).unwrap();
+            assert!(all.iter().any(|m| m.tiles == tiles));
+        }
+    }
+
+    #[test]
+    fn test_save_load() {
+        let book = test_book();
+        let path = std::env::temp_dir().join("sm_checkers_test_book.json");
+        let path = path.to_str().unwrap();
+        book.save(path).unwrap();
+        assert_eq!(OpeningBook::load(path).unwrap(), book);
+        std::fs::remove_file(path).unwrap();
+        assert!(OpeningBook::load(path).is_err());
+    }
+}
@@ -1,51 +1,28 @@
-use std::any::Any;
 use sm_checkers_base::player_colors::Color;
 use sm_checkers_base::movements::*;
 
 
-pub enum ActionType {
-    Quit,
-    Move
-}
-pub trait Action {
-    fn get_type(&self) -> ActionType;
-    fn as_any(&self) -> &dyn Any;
+// What a player can do when it is asked to play.
+// The game controller (CheckersGame) handles every action, see ActionOutcome.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Move(ActionMove),
+    Quit,           // Leaves the program: the game is lost
+    Resign,
+    OfferDraw,      // The opponent answers with AcceptDraw or DeclineDraw, then the player plays
+    AcceptDraw,
+    DeclineDraw,
+    Undo,           // Takes back the last move of the player, and the answer of the opponent
+    Hint            // Asks the controller for a good move
 }
 
-#[derive(Debug)]
-pub struct ActionQuit {}
 
-impl Action for ActionQuit {
-    fn get_type(&self) -> ActionType {
-        ActionType::Quit
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-}
-
-impl ActionQuit {
-    pub fn new() -> ActionQuit {
-        ActionQuit {}
-    }
-}
-
-
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ActionMove {
     pub player_color: Color,
     pub tiles: Vec<usize>
 }
 
-impl Action for ActionMove {
-    fn get_type(&self) -> ActionType {
-        ActionType::Move
-    }
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-}
-
 impl ActionMove {
     pub fn new(player_color: Color, tiles: &Vec<usize>) -> ActionMove {
         ActionMove {
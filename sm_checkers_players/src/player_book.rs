@@ -0,0 +1,133 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::player_colors::Color;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::opening_book::*;
+use crate::bot_level::BotLevel;
+
+// Plays the moves of an opening book, then lets another player (usually the
+// search player) play when the position is not in the book anymore.
+
+pub struct PlayerBook {
+    name: String,
+    color: Color,
+    book: Arc<OpeningBook>,
+    strength: BookStrength,
+    player: Rc<RefCell<dyn Player>>,
+    rng: StdRng,
+    verbose: bool
+}
+
+impl PlayerBook {
+    pub fn new(book: Arc<OpeningBook>, strength: BookStrength, player: Rc<RefCell<dyn Player>>) -> Self {
+        let name = player.borrow().get_name();
+        let color = player.borrow().get_color();
+        PlayerBook {
+            name,
+            color,
+            book,
+            strength,
+            player,
+            rng: StdRng::from_entropy(),
+            verbose: true
+        }
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    pub fn set_strength(&mut self, strength: BookStrength) {
+        self.strength = strength;
+    }
+}
+
+impl Player for PlayerBook {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        if let Some(tiles) = self.book.choose_move(&state.board, self.color, self.strength, &mut self.rng) {
+            if self.verbose {
+                println!("{} - book move: {:?}", self.name, tiles);
+            }
+            return Ok(Action::Move(ActionMove::new(self.color, &tiles)));
+        }
+        self.player.borrow_mut().play_turn(state, legal_moves, time_left)
+    }
+
+    fn answer_draw_offer(&mut self, state: &GameState) -> Action {
+        self.player.borrow_mut().answer_draw_offer(state)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.player.borrow_mut().set_seed(seed);
+    }
+
+    fn start_pondering(&mut self, state: &GameState) {
+        self.player.borrow_mut().start_pondering(state);
+    }
+
+    fn stop_pondering(&mut self) {
+        self.player.borrow_mut().stop_pondering();
+    }
+
+    fn get_level(&self) -> Option<BotLevel> {
+        self.player.borrow().get_level()
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use sm_checkers_base::checkers_rules::CheckersRules;
+    use sm_checkers_base::pdn::parse_pdn;
+    use crate::player_scripted::PlayerScripted;
+
+    #[test]
+    fn test_book_player() {
+        let mut book = OpeningBook::new(10);
+        book.add_games(&parse_pdn("1. 11-15 23-19 2. 8-11 1-0").unwrap());
+        // The fallback only knows a move of the third turn
+        let fallback = Rc::new(RefCell::new(PlayerScripted::new("SCRIPT", Color::Red, &["", "", "", "22-17"])));
+        let mut player = PlayerBook::new(Arc::new(book), BookStrength::Best, fallback);
+        player.set_verbose(false);
+        player.set_seed(1);
+        assert_eq!(player.get_name(), "SCRIPT");
+
+        let mut state = GameState { board: CheckersBoard::new(), next_color: Color::Red, turn: 1, draw_offer: None };
+        let black_moves = CheckersRules::get_legal_movements(&state.board, Color::Black);
+        state.board.move_piece(black_moves.iter().find(|m| m.tiles() == vec![10, 14]).unwrap()).unwrap();
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Red);
+        assert_eq!(player.play_turn(&state, &legal_moves, None), Ok(Action::Move(ActionMove::new(Color::Red, &vec![22, 18]))));
+
+        // Out of the book
+        state.board.move_piece(legal_moves.iter().find(|m| m.tiles() == vec![22, 18]).unwrap()).unwrap();
+        let black_moves = CheckersRules::get_legal_movements(&state.board, Color::Black);
+        state.board.move_piece(black_moves.iter().find(|m| m.tiles() == vec![7, 10]).unwrap()).unwrap();
+        state.turn = 3;
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Red);
+        assert_eq!(player.play_turn(&state, &legal_moves, None), Ok(Action::Move(ActionMove::new(Color::Red, &vec![21, 16]))));
+
+        player.set_strength(BookStrength::Off);
+        state.turn = 1;
+        assert!(player.play_turn(&state, &legal_moves, None).is_err());
+    }
+}
@@ -1,19 +1,69 @@
-use rand::Rng;
+use std::time::Duration;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use sm_checkers_base::checkers_board::*;
-use sm_checkers_base::checkers_rules::*;
 use sm_checkers_base::movements::*;
+use sm_checkers_base::game_result::GameResult;
 use sm_checkers_base::player_colors::*;
 
 use crate::player_trait::*;
 use crate::player_actions::*;
+use crate::evaluator::*;
+use crate::weights_file::*;
+use crate::td_lambda::TdLambdaLearner;
 
 
+// Learner from chapter 1 of Tom Mitchell's "Machine Learning":
+// - The target function V'hat is the linear WeightedFeaturesEvaluator
+// - The move played is the one leading to the board with the highest V'hat
+// - The boards reached by the moves of the player make the trace of the game
+// - After each game, the weights are updated with the LMS rule, using
+//   V_train(b) <- V'hat(Successor(b)) as the training values, the successor
+//   being the next board of the trace
+//   or, when selected, with TD(lambda) (see td_lambda.rs)
+
+
+// Initial weights, so that a new player already prefers to keep its pieces
+const INITIAL_WEIGHTS: [f64; NB_WEIGHTS] = [
+    0.0,    // Bias
+    1.0,    // PlayerPieces
+    -1.0,   // OpponentPieces
+    0.5,    // PlayerKnights
+    -0.5,   // OpponentKnights
+    -0.3,   // OpponentJumps
+    0.3,    // PlayerJumps
+    0.1,    // BackRankGuard
+    0.1,    // CentreControl
+    0.05,   // Mobility
+    0.3,    // Runaways
+    0.02,   // Tempo
+    -0.2,   // ExposedPieces
+    0.05    // KingCentralisation
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LearningRule {
+    Lms,
+    TdLambda(f64)   // lambda
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LearningStats {
+    pub games: usize,
+    pub positions: usize,               // Training examples used by the last game
+    pub mean_squared_error: f64         // Of the last game, before the update
+}
+
 pub struct PlayerBotAI {
     name: String,
     color: Color,
-    board: CheckersBoard,
-    weights: Vec<f64>,
-    eta: f64
+    evaluator: WeightedFeaturesEvaluator,
+    eta: f64,
+    learning_rule: LearningRule,
+    game_trace: Vec<CheckersBoard>,
+    stats: LearningStats,
+    rng: StdRng,   // Breaks the ties between the best movements
+    verbose: bool
 }
 
 impl PlayerBotAI {
@@ -21,93 +71,144 @@ impl PlayerBotAI {
         PlayerBotAI {
             name: name_in.to_owned(),
             color: color_in,
-            board: CheckersBoard::new(),
-            weights: vec![0.0; NB_WEIGHTS],
-            eta: 0.1
+            evaluator: WeightedFeaturesEvaluator::new(&INITIAL_WEIGHTS),
+            eta: 0.1,
+            learning_rule: LearningRule::Lms,
+            game_trace: vec![],
+            stats: LearningStats::default(),
+            rng: StdRng::from_entropy(),
+            verbose: true
         }
     }
-}
 
-const NB_WEIGHTS: usize = 7;
-enum WeightType {
-    Bias,
-    PlayerPieces,
-    OpponentPieces,
-    PlayerKnights,
-    OpponentKnights,
-    OpponentJumps,  // Our number of pieces threatened by the opponnent
-    PlayerJumps    // Number of pieces we are theatening
+    // Loads the weights and the learning metadata saved by save_weights_file
+    pub fn from_weights_file(name_in: & str, color_in: Color, path: &str) -> Result<Self, String> {
+        let file = WeightsFile::load(path)?;
+        let mut player = PlayerBotAI::new(name_in, color_in);
+        player.evaluator.set_weights(&file.weights);
+        player.eta = file.learning.eta;
+        if let Some(lambda) = file.learning.td_lambda {
+            player.learning_rule = LearningRule::TdLambda(lambda);
+        }
+        player.stats.games = file.learning.games;
+        player.stats.mean_squared_error = file.learning.mean_squared_error;
+        Ok(player)
+    }
+
+    pub fn save_weights_file(&self, path: &str) -> Result<(), String> {
+        let learning = LearningMetadata {
+            eta: self.eta,
+            td_lambda: match self.learning_rule {
+                LearningRule::Lms => None,
+                LearningRule::TdLambda(lambda) => Some(lambda)
+            },
+            games: self.stats.games,
+            mean_squared_error: self.stats.mean_squared_error
+        };
+        WeightsFile::new(self.evaluator.get_weights(), learning).save(path)
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    // The weights are relative to the player, they can be used with both colors
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn set_weights(&mut self, weights: &[f64]) {
+        self.evaluator.set_weights(weights);
+    }
+
+    // Forgets the boards of the previous game, without learning from them
+    pub fn new_game(&mut self) {
+        self.game_trace.clear();
+    }
+
+    pub fn get_evaluator(&self) -> &WeightedFeaturesEvaluator {
+        &self.evaluator
+    }
+
+    // Learning rate of both rules (alpha for TD(lambda))
+    pub fn set_eta(&mut self, eta: f64) {
+        self.eta = eta;
+    }
+
+    pub fn set_learning_rule(&mut self, rule: LearningRule) {
+        self.learning_rule = rule;
+    }
+
+    pub fn get_learning_stats(&self) -> &LearningStats {
+        &self.stats
+    }
 }
 
 
 impl PlayerBotAI {
     // This is the V'hat function
-    fn get_board_value_approx(&self) -> f64 {
-        // W0
-        let mut result: f64 = self.weights[WeightType::Bias as usize];
-
-        let my_pieces = CheckersRules::get_player_pieces_indexes(&self.board, self.color);
-        let mut my_knights_tile_state = TileState::BlackKnight;
-        if self.color == Color::Red {
-            my_knights_tile_state = TileState::RedKnight;
-        }
-        let my_knights_count = my_pieces.iter().filter(|&x| self.board.tiles[*x] == my_knights_tile_state).count();
+    fn get_board_value_approx(&self, board: &CheckersBoard) -> f64 {
+        self.evaluator.evaluate(board, self.color)
+    }
 
-        let opp_pieces = CheckersRules::get_player_pieces_indexes(&self.board, opposite_color(self.color));
-        let mut opp_knights_tile_state = TileState::RedKnight;
-        if self.color == Color::Red {
-            opp_knights_tile_state = TileState::BlackKnight;
+    // Updates the weights from the boards seen during the game, then forgets them.
+    // Each board b of the trace is a training example, with:
+    //   V_train(b) = V'hat(b'), where b' is the next board with the same player to move
+    //   V_train(b) = final value of the game, for the last boards
+    pub fn learn_from_game(&mut self, result: &GameResult) {
+        if let LearningRule::TdLambda(lambda) = self.learning_rule {
+            let trace = std::mem::take(&mut self.game_trace);
+            let learner = TdLambdaLearner::new(lambda, self.eta);
+            let error = learner.learn_from_game(&mut self.evaluator, &trace, self.color, result);
+            self.update_stats(trace.len(), error);
+            return;
         }
-        let opp_knights_count = opp_pieces.iter().filter(|&x| self.board.tiles[*x] == opp_knights_tile_state).count();
-        
-        // W1
-        result += self.weights[WeightType::PlayerPieces as usize] * my_pieces.len() as f64;
-        // W2
-        result += self.weights[WeightType::OpponentPieces as usize] * opp_pieces.len() as f64;
-        // W3
-        result += self.weights[WeightType::PlayerKnights as usize] * my_knights_count as f64;
-        // W4
-        result += self.weights[WeightType::OpponentKnights as usize] * opp_knights_count as f64;
-        // W5
-        let mut opp_jumps_count = 0;
-        for piece in &opp_pieces {
-            opp_jumps_count += CheckersRules::get_possible_jumps(&self.board, *piece).len();
+
+        let final_value = result.get_value(self.color);
+        let trace = std::mem::take(&mut self.game_trace);
+
+        let mut training_examples = vec![];
+        for (i, board) in trace.iter().enumerate() {
+            let v_train = match trace.get(i + 1) {
+                Some(successor) => self.get_board_value_approx(successor),
+                None => final_value
+            };
+            training_examples.push((board, v_train));
         }
-        result += self.weights[WeightType::OpponentJumps as usize] * opp_jumps_count as f64;
-        // W6
-        let mut my_jumps_count = 0;
-        for piece in &my_pieces {
-            my_jumps_count += CheckersRules::get_possible_jumps(&self.board, *piece).len();
+
+        let mut squared_error_sum = 0.0;
+        for (board, v_train) in training_examples.iter() {
+            let error = v_train - self.get_board_value_approx(board);
+            squared_error_sum += error * error;
+            self.update_weights(board, error);
         }
-        result += self.weights[WeightType::PlayerJumps as usize] * my_jumps_count as f64;
-        result
+
+        let mean_squared_error = if training_examples.is_empty() { 0.0 } else { squared_error_sum / training_examples.len() as f64 };
+        self.update_stats(training_examples.len(), mean_squared_error);
     }
 
-    /*
-    fn update_weights(&mut self, error: f64) {
-        for i in 0..NB_WEIGHTS {
-            self.weights[i] += self.eta * error * self.get_attribute_value(i as WeightType);
+    fn update_stats(&mut self, positions: usize, mean_squared_error: f64) {
+        self.stats.games += 1;
+        self.stats.positions = positions;
+        self.stats.mean_squared_error = mean_squared_error;
+        if self.verbose {
+            println!("{} - game #{}: learned from {} positions, mean squared error: {:.3}",
+                self.name, self.stats.games, self.stats.positions, self.stats.mean_squared_error);
         }
     }
 
-    fn get_attribute_value(&self, attribute: WeightType) -> f64 {
-        match attribute {
-            WeightType::Bias => 1.0,
-            WeightType::PlayerPieces => 1.0,
-            WeightType::OpponentPieces => 1.0,
-            WeightType::PlayerKnights => 1.0,
-            WeightType::OpponentKnights => 1.0,
-            WeightType::OpponentJumps => 1.0,
-            WeightType::PlayerJumps => 1.0
+    // LMS rule: wi <- wi + eta * (V_train(b) - V'hat(b)) * xi
+    // The step is divided by the squared norm of the features (normalized LMS),
+    // otherwise features like Tempo, that can reach tens, make the weights diverge.
+    fn update_weights(&mut self, board: &CheckersBoard, error: f64) {
+        let features = WeightedFeaturesEvaluator::get_features(board, self.color);
+        let norm: f64 = features.iter().map(|x| x * x).sum();
+        let mut weights = self.evaluator.get_weights().to_vec();
+        for i in 0..NB_WEIGHTS {
+            weights[i] += self.eta * error * features[i] / norm;
         }
+        self.evaluator.set_weights(&weights);
     }
-    fn choose_move(&self) -> Box<dyn Action> {
-        
-    )}
-
-    */
-
-
 }
 
 impl Player for PlayerBotAI {
@@ -119,55 +220,106 @@ impl Player for PlayerBotAI {
         self.name.clone()
     }
 
-    fn play_turn(&self) -> Box<dyn Action> {
-        // Find all of my pieces
-        let mut pieces = CheckersRules::get_player_pieces_indexes(&self.board, self.color);
-        //println!("{} - pieces: {:?}", self.name, pieces);
-        // Check if a jump is possible, one piece at a time, randomly
-        let mut pieces_for_jump = pieces.clone();
-        while !pieces_for_jump.is_empty() {
-            let rand_piece_idx = rand::thread_rng().gen_range(0..pieces_for_jump.len());
-            let jumps = CheckersRules::get_possible_jumps(&self.board, pieces_for_jump[rand_piece_idx]);
-            if !jumps.is_empty() {
-                let jump_choice = rand::thread_rng().gen_range(0..jumps.len());
-                let mut jump_vec = vec![jumps[jump_choice].from()];
-                jump_vec.extend(jumps[jump_choice].to.clone());
-                let action = ActionMove::new(self.color, &jump_vec);
-                println!("{} - jumping: {:?}", self.name, action);
-                return Box::new(action);                
-            }
-            else {
-                pieces_for_jump.remove(rand_piece_idx);
-            }
-        }
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
 
-        // Check if a shift is possible, one piece at a time, randomly
-        while !pieces.is_empty() {
-            let rand_piece_idx = rand::thread_rng().gen_range(0..pieces.len());
-            let shifts = CheckersRules::get_possible_shifts(&self.board, pieces[rand_piece_idx]);
-            if !shifts.is_empty() {
-                let shift_choice = rand::thread_rng().gen_range(0..shifts.len());
-                let mut shift_vec = vec![shifts[shift_choice].from()];
-                shift_vec.push(shifts[shift_choice].to);
-                let action = ActionMove::new(self.color, &shift_vec);
-                println!("{} - piece idx: {}, choice: {}, shifting: {:?}", self.name, rand_piece_idx, shift_choice, action);
-                return Box::new(action);                
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], _time_left: Option<Duration>) -> Result<Action, String> {
+        // Play the movement leading to the best board, according to V'hat
+        let mut best_value = f64::NEG_INFINITY;
+        let mut best_boards = vec![];
+        for m in legal_moves.iter() {
+            let mut next_board = state.board.clone();
+            next_board.move_piece(m)?;
+            let value = self.get_board_value_approx(&next_board);
+            if value > best_value {
+                best_value = value;
+                best_boards.clear();
             }
-            else {
-                pieces.remove(rand_piece_idx);
+            if value == best_value {
+                best_boards.push((m, next_board));
             }
         }
 
-        // Can't jump or can't shift !!
-        // Seems like the game should be over...
-        // Panicking for now
-        panic!("{} - Game should be over, I can't find a move to do.", self.name);
+        if best_boards.is_empty() {
+            return Err(format!("{} - Game should be over, I can't find a move to do.", self.name));
+        }
+        let choice = self.rng.gen_range(0..best_boards.len());
+        let (movement, next_board) = best_boards.swap_remove(choice);
+        self.game_trace.push(next_board);
+        let action = ActionMove::new(self.color, &movement.tiles());
+        if self.verbose {
+            println!("{} - value: {:.3}, moving: {:?}", self.name, best_value, action);
+        }
+        Ok(Action::Move(action))
     }
 }
 
-impl GameBoardObserver for PlayerBotAI {
-    fn update(&mut self, board: &CheckersBoard) {
-        self.board = (*board).clone();
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learn_from_game() {
+        let mut player = PlayerBotAI::new("AI", Color::Black);
+        assert!(player.get_evaluator().get_weights().iter().any(|w| *w != 0.0));
+
+        // Black won with more pieces: the final boards must get closer to +100
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[20] = TileState::BlackKnight;
+        board.tiles[21] = TileState::BlackKnight;
+        board.tiles[30] = TileState::RedMan;
+        player.game_trace.push(board.clone());
+        board.tiles[30] = TileState::Empty;
+        player.game_trace.push(board.clone());
+
+        let value_before = player.get_board_value_approx(&board);
+        player.learn_from_game(&GameResult::Win(Color::Black));
+        let value_after = player.get_board_value_approx(&board);
+        assert!(value_after > value_before);
+        assert!(value_after < 100.0);
+
+        let stats = player.get_learning_stats();
+        assert_eq!(stats.games, 1);
+        assert_eq!(stats.positions, 2);
+        assert!(stats.mean_squared_error > 0.0);
+
+        // The trace was consumed
+        player.learn_from_game(&GameResult::Draw);
+        assert_eq!(player.get_learning_stats().positions, 0);
+
+        // The learned weights survive a save and a load
+        let path = std::env::temp_dir().join("sm_checkers_test_ai_weights.json");
+        let path = path.to_str().unwrap();
+        player.save_weights_file(path).unwrap();
+        let loaded = PlayerBotAI::from_weights_file("AI 2", Color::Red, path).unwrap();
+        assert_eq!(loaded.get_evaluator().get_weights(), player.get_evaluator().get_weights());
+        assert_eq!(loaded.get_learning_stats().games, 2);
+        std::fs::remove_file(path).unwrap();
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_learn_from_game_td_lambda() {
+        let mut player = PlayerBotAI::new("AI", Color::Red);
+        player.set_learning_rule(LearningRule::TdLambda(0.8));
+
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[4] = TileState::RedKnight;
+        board.tiles[5] = TileState::RedKnight;
+        board.tiles[12] = TileState::BlackMan;
+        player.game_trace.push(board.clone());
+        board.tiles[12] = TileState::Empty;
+        player.game_trace.push(board.clone());
+
+        let value_before = player.get_board_value_approx(&board);
+        player.learn_from_game(&GameResult::Win(Color::Red));
+        assert!(player.get_board_value_approx(&board) > value_before);
+        assert_eq!(player.get_learning_stats().positions, 2);
+    }
+}
@@ -1,4 +1,6 @@
-use rand::Rng;
+use std::time::Duration;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use sm_checkers_base::checkers_board::*;
 use sm_checkers_base::checkers_rules::*;
 use sm_checkers_base::movements::*;
@@ -10,7 +12,8 @@ use crate::player_actions::*;
 pub struct PlayerBotRandom {
     name: String,
     color: Color,
-    board: CheckersBoard
+    rng: StdRng,
+    verbose: bool
 }
 
 impl PlayerBotRandom {
@@ -18,9 +21,28 @@ impl PlayerBotRandom {
         PlayerBotRandom {
             name: name_in.to_owned(),
             color: color_in,
-            board: CheckersBoard::new()
+            rng: StdRng::from_entropy(),
+            verbose: true
         }
     }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+}
+
+// A legal move chosen uniformly, multi-jumps included.
+// Returns the tiles of the move, None when the player can't move.
+pub fn choose_random_move<R: Rng>(board: &CheckersBoard, color: Color, rng: &mut R) -> Option<Vec<usize>> {
+    let movements = CheckersRules::get_legal_movements(board, color);
+    choose_random_movement(&movements, rng)
+}
+
+fn choose_random_movement<R: Rng>(movements: &[Box<dyn Movement>], rng: &mut R) -> Option<Vec<usize>> {
+    if movements.is_empty() {
+        return None;
+    }
+    Some(movements[rng.gen_range(0..movements.len())].tiles())
 }
 
 impl Player for PlayerBotRandom {
@@ -32,55 +54,64 @@ impl Player for PlayerBotRandom {
         self.name.clone()
     }
 
-    fn play_turn(&self) -> Box<dyn Action> {
-        // Find all of my pieces
-        let mut pieces = CheckersRules::get_player_pieces_indexes(&self.board, self.color);
-        //println!("{} - pieces: {:?}", self.name, pieces);
-        // Check if a jump is possible, one piece at a time, randomly
-        let mut pieces_for_jump = pieces.clone();
-        while !pieces_for_jump.is_empty() {
-            let rand_piece_idx = rand::thread_rng().gen_range(0..pieces_for_jump.len());
-            let jumps = CheckersRules::get_possible_jumps(&self.board, pieces_for_jump[rand_piece_idx]);
-            if !jumps.is_empty() {
-                let jump_choice = rand::thread_rng().gen_range(0..jumps.len());
-                let mut jump_vec = vec![jumps[jump_choice].from()];
-                jump_vec.extend(jumps[jump_choice].to.clone());
-                let action = ActionMove::new(self.color, &jump_vec);
-                println!("{} - jumping: {:?}", self.name, action);
-                return Box::new(action);                
-            }
-            else {
-                pieces_for_jump.remove(rand_piece_idx);
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn play_turn(&mut self, _state: &GameState, legal_moves: &[Box<dyn Movement>], _time_left: Option<Duration>) -> Result<Action, String> {
+        match choose_random_movement(legal_moves, &mut self.rng) {
+            Some(tiles) => {
+                let action = ActionMove::new(self.color, &tiles);
+                if self.verbose {
+                    println!("{} - playing: {:?}", self.name, action);
+                }
+                Ok(Action::Move(action))
             }
+            // Can't jump or can't shift !!
+            // Seems like the game should be over...
+            None => Err(format!("{} - Game should be over, I can't find a move to do.", self.name))
         }
+    }
+}
 
-        // Check if a shift is possible, one piece at a time, randomly
-        while !pieces.is_empty() {
-            let rand_piece_idx = rand::thread_rng().gen_range(0..pieces.len());
-            let shifts = CheckersRules::get_possible_shifts(&self.board, pieces[rand_piece_idx]);
-            if !shifts.is_empty() {
-                let shift_choice = rand::thread_rng().gen_range(0..shifts.len());
-                let mut shift_vec = vec![shifts[shift_choice].from()];
-                shift_vec.push(shifts[shift_choice].to);
-                let action = ActionMove::new(self.color, &shift_vec);
-                println!("{} - piece idx: {}, choice: {}, shifting: {:?}", self.name, rand_piece_idx, shift_choice, action);
-                return Box::new(action);                
-            }
-            else {
-                pieces.remove(rand_piece_idx);
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::notation::fen_to_board;
+
+    #[test]
+    fn test_random_multi_jump() {
+        // The only legal move takes both red men
+        let (board, color) = fen_to_board("B:W6,15:B1").unwrap();
+        let legal_moves = CheckersRules::get_legal_movements(&board, color);
+        let state = GameState { board: board.clone(), next_color: color, turn: 0, draw_offer: None };
+        let mut player = PlayerBotRandom::new("random", color);
+        player.set_verbose(false);
+        for seed in 0..10 {
+            player.set_seed(seed);
+            assert_eq!(choose_random_move(&board, color, &mut player.rng), Some(vec![0, 9, 18]));
+            match player.play_turn(&state, &legal_moves, None) {
+                Ok(Action::Move(action_move)) => assert_eq!(action_move.tiles, vec![0, 9, 18]),
+                other => panic!("Unexpected action {:?}", other)
             }
         }
 
-        // Can't jump or can't shift !!
-        // Seems like the game should be over...
-        // Panicking for now
-        panic!("{} - Game should be over, I can't find a move to do.", self.name);
+        // Among the given legal moves only
+        let board = CheckersBoard::new();
+        let legal_moves = CheckersRules::get_legal_movements(&board, Color::Black);
+        let state = GameState { board: board.clone(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let mut player = PlayerBotRandom::new("random", Color::Black);
+        player.set_verbose(false);
+        for _ in 0..20 {
+            match player.play_turn(&state, &legal_moves[..2], None) {
+                Ok(Action::Move(action_move)) => assert!(legal_moves[..2].iter().any(|m| m.tiles() == action_move.tiles)),
+                other => panic!("Unexpected action {:?}", other)
+            }
+        }
+        assert!(player.play_turn(&state, &[], None).is_err());
     }
 }
-
-impl GameBoardObserver for PlayerBotRandom {
-    fn update(&mut self, board: &CheckersBoard) {
-        self.board = (*board).clone();
-    }
-
-}
\ No newline at end of file
@@ -0,0 +1,864 @@
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use sm_checkers_base::checkers_board::*;
+use sm_checkers_base::checkers_rules::*;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::player_colors::*;
+use sm_checkers_base::zobrist::hash_board;
+use sm_checkers_base::notation::movement_to_string;
+use sm_checkers_base::endgame_db::DbValue;
+use sm_checkers_base::endgame_probe::EndgameProbe;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::transposition_table::*;
+use crate::evaluator::*;
+use crate::bot_level::*;
+
+// Alpha-beta player with iterative deepening.
+//
+// The search runs on several threads with the "Lazy SMP" scheme: every thread
+// searches the same root position, and they only cooperate through the shared
+// transposition table. The result of the main thread (thread 0) is played.
+//
+// With endgame databases, the positions they know are not searched: a known
+// win scores DB_WIN_SCORE plus the evaluation, so that the search still
+// heads for the captures and promotions that convert it.
+//
+// A level (bot_level.rs) limits the search, adds noise to the evaluations
+// (the same noise for a position during a game, whatever the thread) and
+// makes the player blunder with a random move from time to time.
+//
+// With pondering, the player searches in the background while the opponent
+// thinks, the position after the reply it expects (the best move of the
+// transposition table). When the opponent plays this reply, the search is
+// done already, or at least the transposition table is filled.
+
+pub const WIN_SCORE: i32 = 100_000;
+const MAX_PLY: i32 = 128;
+// The evaluators return values in "men": the search works in hundredths of a man
+const EVAL_SCALE: f64 = 100.0;
+const NODES_BETWEEN_TIME_CHECKS: u64 = 1024;
+pub const DB_WIN_SCORE: i32 = WIN_SCORE / 2;
+const DB_EVAL_LIMIT: i32 = WIN_SCORE / 10;
+
+pub const DEFAULT_HASH_SIZE_MB: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub tiles: Vec<usize>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub forced: bool        // The only legal move, not searched
+}
+
+// A move of an analysis, with the moves expected after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisLine {
+    pub tiles: Vec<usize>,
+    pub score: i32,
+    pub depth: u32,
+    pub pv: Vec<Vec<usize>>     // The principal variation, starting with tiles
+}
+
+impl AnalysisLine {
+    // "11-15 (0.12, depth 6: 11-15 23-19 8-11)", color: the color playing the move
+    pub fn to_notation(&self, color: Color) -> String {
+        let mut moves = vec![];
+        let mut mover = color;
+        for tiles in self.pv.iter() {
+            moves.push(movement_to_string(ActionMove::new(mover, tiles).to_movement().as_ref()));
+            mover = opposite_color(mover);
+        }
+        let score = if self.score.abs() > WIN_SCORE - MAX_PLY {
+            if self.score > 0 { "win".to_string() } else { "loss".to_string() }
+        } else {
+            format!("{:.2}", self.score as f64 / EVAL_SCALE)
+        };
+        format!("{} ({}, depth {}: {})", moves[0], score, self.depth, moves.join(" "))
+    }
+}
+
+pub struct PlayerBotSearch {
+    name: String,
+    color: Color,
+    threads: usize,
+    max_depth: u32,
+    max_time: Duration,
+    tt: Arc<TranspositionTable>,
+    evaluator: Arc<dyn Evaluator>,
+    endgame_db: Option<Arc<EndgameProbe>>,
+    level: Option<BotLevel>,
+    eval_noise: i32,            // In hundredths of a man
+    blunder_probability: f64,
+    noise_seed: u64,
+    rng: StdRng,
+    ponder: bool,
+    pondering: Option<Ponder>,
+    verbose: bool
+}
+
+// A search running on the time of the opponent
+struct Ponder {
+    board: CheckersBoard,       // After the expected reply
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Option<SearchResult>>
+}
+
+// The pondering search stops at the maximum depth, or when the opponent plays
+const PONDER_MAX_TIME: Duration = Duration::from_secs(24 * 3600);
+// The time of a search of depth 1 is not limited by the clock
+const DEPTH_1_MAX_TIME: Duration = Duration::from_secs(3600);
+
+impl PlayerBotSearch {
+    pub fn new(name_in: & str, color_in: Color) -> Self {
+        Self::with_transposition_table(name_in, color_in, Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE_MB)))
+    }
+
+    // A player searching with a table shared with other searches, from the same evaluator
+    pub fn with_transposition_table(name_in: & str, color_in: Color, tt: Arc<TranspositionTable>) -> Self {
+        PlayerBotSearch {
+            name: name_in.to_owned(),
+            color: color_in,
+            threads: 1,
+            max_depth: 10,
+            max_time: Duration::from_secs(1),
+            tt,
+            evaluator: Arc::new(MaterialEvaluator::new()),
+            endgame_db: None,
+            level: None,
+            eval_noise: 0,
+            blunder_probability: 0.0,
+            noise_seed: 0,
+            rng: StdRng::from_entropy(),
+            ponder: false,
+            pondering: None,
+            verbose: true
+        }
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    pub fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator>) {
+        self.stop_pondering();
+        self.evaluator = evaluator;
+        // Scores stored with the previous evaluator are meaningless now
+        self.tt.clear();
+    }
+
+    pub fn set_endgame_db(&mut self, endgame_db: Arc<EndgameProbe>) {
+        self.stop_pondering();
+        self.endgame_db = Some(endgame_db);
+        // Scores stored without the databases are less accurate
+        self.tt.clear();
+    }
+
+    pub fn set_level(&mut self, level: BotLevel) {
+        self.stop_pondering();
+        let settings = level.settings();
+        self.level = Some(level);
+        self.max_depth = settings.max_depth;
+        self.max_time = settings.max_time;
+        self.eval_noise = (settings.eval_noise * EVAL_SCALE).round() as i32;
+        self.blunder_probability = settings.blunder_probability;
+        // Scores stored with another noise are meaningless now
+        self.tt.clear();
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: u32) {
+        self.max_depth = max_depth.max(1);
+    }
+
+    pub fn set_max_time(&mut self, max_time: Duration) {
+        self.max_time = max_time;
+    }
+
+    pub fn set_pondering(&mut self, ponder: bool) {
+        self.ponder = ponder;
+        if !ponder {
+            self.stop_pondering();
+        }
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    // The result of the pondering search, if it searched this position
+    fn take_ponder_result(&mut self, board: &CheckersBoard) -> Option<SearchResult> {
+        let ponder = self.pondering.take()?;
+        ponder.stop.store(true, Ordering::Relaxed);
+        let result = ponder.handle.join().ok().flatten();
+        if ponder.board.tiles == board.tiles { result } else { None }
+    }
+
+    pub fn search(&self, board: &CheckersBoard, color: Color) -> Option<SearchResult> {
+        self.search_with_time(board, color, self.max_time)
+    }
+
+    fn search_with_time(&self, board: &CheckersBoard, color: Color, max_time: Duration) -> Option<SearchResult> {
+        let stop = AtomicBool::new(false);
+        self.context().search(board, color, Instant::now() + max_time, &stop)
+    }
+
+    fn context(&self) -> SearchContext {
+        SearchContext {
+            threads: self.threads,
+            max_depth: self.max_depth,
+            tt: self.tt.clone(),
+            evaluator: self.evaluator.clone(),
+            endgame_db: self.endgame_db.clone(),
+            eval_noise: self.eval_noise,
+            noise_seed: self.noise_seed
+        }
+    }
+
+    // The best moves of the position, the best first, at most lines of them.
+    // Every root move is searched at each depth, on one thread, with the
+    // depth and time of the player. The moves that cannot be among the best
+    // ones are searched with a narrower window. The results of the last
+    // completed depth are returned, with their principal variations read
+    // from the transposition table.
+    pub fn analyze(&self, board: &CheckersBoard, color: Color, lines: usize) -> Vec<AnalysisLine> {
+        let root_moves = CheckersRules::get_legal_movements(board, color);
+        if lines == 0 || root_moves.is_empty() {
+            return vec![];
+        }
+        let stop = AtomicBool::new(false);
+        let nodes = AtomicU64::new(0);
+        let context = self.context();
+        let mut searcher = context.searcher(&stop, &nodes, Instant::now() + self.max_time);
+
+        let mut analysis = vec![];
+        let mut order: Vec<usize> = (0..root_moves.len()).collect();
+        for depth in 1..=self.max_depth {
+            let mut scores: Vec<(usize, i32)> = vec![];
+            for index in order.iter().copied() {
+                // Lower than the last of the best lines: not one of them
+                let alpha = if scores.len() >= lines {
+                    let mut best: Vec<i32> = scores.iter().map(|(_, score)| *score).collect();
+                    best.sort_unstable_by(|a, b| b.cmp(a));
+                    best[lines - 1]
+                } else {
+                    -WIN_SCORE - 1
+                };
+                let mut child = board.clone();
+                child.move_piece(&root_moves[index]).unwrap();
+                let score = -searcher.negamax(&child, opposite_color(color), depth as i32 - 1, 1, -WIN_SCORE - 1, -alpha);
+                if stop.load(Ordering::Relaxed) {
+                    return analysis;
+                }
+                scores.push((index, score));
+            }
+            scores.sort_by_key(|s| Reverse(s.1));
+            order = scores.iter().map(|(index, _)| *index).collect();
+            analysis = scores.iter().take(lines).map(|(index, score)| {
+                let mut child = board.clone();
+                child.move_piece(&root_moves[*index]).unwrap();
+                let mut pv = vec![root_moves[*index].tiles()];
+                pv.extend(self.principal_variation(&child, opposite_color(color), depth as usize - 1));
+                AnalysisLine { tiles: root_moves[*index].tiles(), score: *score, depth, pv }
+            }).collect();
+        }
+        analysis
+    }
+
+    // The best moves of the transposition table from the position
+    fn principal_variation(&self, board: &CheckersBoard, color: Color, max_len: usize) -> Vec<Vec<usize>> {
+        let mut pv = vec![];
+        let mut board = board.clone();
+        let mut color = color;
+        while pv.len() < max_len {
+            let moves = CheckersRules::get_legal_movements(&board, color);
+            let best = self.tt.probe(hash_board(&board, color)).and_then(|entry| entry.best_move_index).map(|m| m as usize);
+            match best.filter(|m| *m < moves.len()) {
+                Some(index) => {
+                    pv.push(moves[index].tiles());
+                    board.move_piece(&moves[index]).unwrap();
+                    color = opposite_color(color);
+                }
+                None => break
+            }
+        }
+        pv
+    }
+}
+
+// What the search threads share, owned by the pondering thread
+#[derive(Clone)]
+struct SearchContext {
+    threads: usize,
+    max_depth: u32,
+    tt: Arc<TranspositionTable>,
+    evaluator: Arc<dyn Evaluator>,
+    endgame_db: Option<Arc<EndgameProbe>>,
+    eval_noise: i32,
+    noise_seed: u64
+}
+
+impl SearchContext {
+    fn searcher<'a>(&'a self, stop: &'a AtomicBool, nodes: &'a AtomicU64, deadline: Instant) -> Searcher<'a> {
+        Searcher {
+            tt: self.tt.as_ref(),
+            evaluator: self.evaluator.as_ref(),
+            endgame_db: self.endgame_db.as_deref(),
+            eval_noise: self.eval_noise,
+            noise_seed: self.noise_seed,
+            stop,
+            nodes,
+            deadline,
+            local_nodes: 0
+        }
+    }
+
+    // Searches until the maximum depth, the deadline, or until another thread sets stop
+    fn search(&self, board: &CheckersBoard, color: Color, deadline: Instant, stop: &AtomicBool) -> Option<SearchResult> {
+        let root_moves = CheckersRules::get_legal_movements(board, color);
+        if root_moves.is_empty() {
+            return None;
+        }
+        if root_moves.len() == 1 {
+            // Forced move, no need to think
+            return Some(SearchResult { tiles: root_moves[0].tiles(), score: 0, depth: 0, nodes: 0, forced: true });
+        }
+
+        let nodes = AtomicU64::new(0);
+        let result = std::thread::scope(|scope| {
+            for thread_id in 1..self.threads {
+                let nodes = &nodes;
+                scope.spawn(move || {
+                    self.searcher(stop, nodes, deadline).iterative_deepening(board, color, self.max_depth, thread_id);
+                });
+            }
+
+            let result = self.searcher(stop, &nodes, deadline).iterative_deepening(board, color, self.max_depth, 0);
+            // The helpers have nothing more to contribute once the main thread is done
+            stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        // Depth 1 is completed whatever the deadline or the stop, so that the move played was searched
+        let result = result.or_else(|| {
+            let no_stop = AtomicBool::new(false);
+            self.searcher(&no_stop, &nodes, Instant::now() + DEPTH_1_MAX_TIME).iterative_deepening(board, color, 1, 0)
+        });
+        let (best_index, score, depth) = result?;
+        Some(SearchResult {
+            tiles: root_moves[best_index].tiles(),
+            score,
+            depth,
+            nodes: nodes.load(Ordering::Relaxed),
+            forced: false
+        })
+    }
+}
+
+struct Searcher<'a> {
+    tt: &'a TranspositionTable,
+    evaluator: &'a dyn Evaluator,
+    endgame_db: Option<&'a EndgameProbe>,
+    eval_noise: i32,
+    noise_seed: u64,
+    stop: &'a AtomicBool,
+    nodes: &'a AtomicU64,
+    deadline: Instant,
+    local_nodes: u64
+}
+
+impl<'a> Searcher<'a> {
+    // Returns the best root move index, its score and the depth of the last completed iteration
+    fn iterative_deepening(&mut self, board: &CheckersBoard, color: Color, max_depth: u32, thread_id: usize) -> Option<(usize, i32, u32)> {
+        let mut best = None;
+        // Helper threads start one ply deeper every other thread, so that they
+        // fill the table with different results than the main thread
+        let first_depth = 1 + (thread_id % 2) as u32;
+        for depth in first_depth..=max_depth {
+            let score = self.negamax(board, color, depth as i32, 0, -WIN_SCORE - 1, WIN_SCORE + 1);
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let hash = hash_board(board, color);
+            if let Some(entry) = self.tt.probe(hash) {
+                if let Some(index) = entry.best_move_index {
+                    best = Some((index as usize, score, depth));
+                }
+            }
+            // No need to search deeper when the outcome is known
+            if score.abs() > WIN_SCORE - MAX_PLY {
+                break;
+            }
+        }
+        self.nodes.fetch_add(self.local_nodes % NODES_BETWEEN_TIME_CHECKS, Ordering::Relaxed);
+        best
+    }
+
+    fn negamax(&mut self, board: &CheckersBoard, color: Color, depth: i32, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        self.local_nodes += 1;
+        if self.local_nodes.is_multiple_of(NODES_BETWEEN_TIME_CHECKS) {
+            self.nodes.fetch_add(NODES_BETWEEN_TIME_CHECKS, Ordering::Relaxed);
+            if Instant::now() >= self.deadline {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return 0;
+        }
+
+        let moves = CheckersRules::get_legal_movements(board, color);
+        if moves.is_empty() {
+            // The player to move cannot move: lost. Losing later is better.
+            return -WIN_SCORE + ply;
+        }
+        let is_capture = moves[0].as_any().downcast_ref::<Jump>().is_some();
+        if ply >= MAX_PLY || (depth <= 0 && !is_capture) {
+            return self.evaluate(board, color);
+        }
+        if ply > 0 {
+            if let Some(probe) = self.endgame_db.and_then(|db| db.probe(board, color)) {
+                let eval = self.evaluate(board, color).clamp(-DB_EVAL_LIMIT, DB_EVAL_LIMIT);
+                return match probe.value {
+                    DbValue::Win => DB_WIN_SCORE + eval,
+                    DbValue::Loss => -DB_WIN_SCORE + eval,
+                    DbValue::Draw => 0
+                };
+            }
+        }
+
+        let hash = hash_board(board, color);
+        let original_alpha = alpha;
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(hash) {
+            tt_move = entry.best_move_index.map(|m| m as usize).filter(|m| *m < moves.len());
+            if ply > 0 && entry.depth as i32 >= depth {
+                let score = score_from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        // Search the move suggested by the table first
+        let mut order: Vec<usize> = (0..moves.len()).collect();
+        if let Some(m) = tt_move {
+            order.swap(0, m);
+        }
+
+        let mut best_score = -WIN_SCORE - 1;
+        let mut best_index = order[0];
+        for index in order {
+            let mut child = board.clone();
+            child.move_piece(&moves[index]).unwrap();
+            let score = -self.negamax(&child, opposite_color(color), depth - 1, ply + 1, -beta, -alpha);
+            if self.stop.load(Ordering::Relaxed) {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_index = index;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        }
+        else if best_score >= beta {
+            Bound::Lower
+        }
+        else {
+            Bound::Exact
+        };
+        self.tt.store(hash, TtEntry {
+            score: score_to_tt(best_score, ply),
+            depth: depth.clamp(0, u8::MAX as i32) as u8,
+            bound,
+            best_move_index: Some(best_index as u8)
+        });
+        best_score
+    }
+
+    fn evaluate(&self, board: &CheckersBoard, color: Color) -> i32 {
+        // Static values must stay below the win scores
+        let limit = (WIN_SCORE - MAX_PLY - 1) as f64;
+        let score = (self.evaluator.evaluate(board, color) * EVAL_SCALE).round().clamp(-limit, limit) as i32;
+        if self.eval_noise == 0 {
+            return score;
+        }
+        // The noise of a position comes from its hash: no generator shared by the threads
+        let mut bits = hash_board(board, color) ^ self.noise_seed;
+        bits ^= bits >> 33;
+        bits = bits.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+        bits ^= bits >> 33;
+        let noise = (bits % (2 * self.eval_noise as u64 + 1)) as i32 - self.eval_noise;
+        (score + noise).clamp(-limit as i32, limit as i32)
+    }
+}
+
+// Win scores depend on the distance to the root, the table stores them relative to the node
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > WIN_SCORE - MAX_PLY {
+        score + ply
+    }
+    else if score < -WIN_SCORE + MAX_PLY {
+        score - ply
+    }
+    else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > WIN_SCORE - MAX_PLY {
+        score - ply
+    }
+    else if score < -WIN_SCORE + MAX_PLY {
+        score + ply
+    }
+    else {
+        score
+    }
+}
+
+impl Player for PlayerBotSearch {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        // Never more than the time left on the clock
+        let max_time = time_left.map_or(self.max_time, |t| self.max_time.min(t));
+        // The pondering search is played when it went as deep as this search would
+        let pondered = self.take_ponder_result(&state.board);
+        if self.verbose && pondered.is_some() {
+            println!("{} - ponder hit", self.name);
+        }
+        let searched = match pondered {
+            Some(result) if result.forced || result.depth >= self.max_depth => Some(result),
+            _ => self.search_with_time(&state.board, self.color, max_time)
+        };
+        match searched {
+            Some(mut result) => {
+                if legal_moves.len() > 1 && self.blunder_probability > 0.0 && self.rng.gen_bool(self.blunder_probability) {
+                    let others: Vec<Vec<usize>> = legal_moves.iter().map(|m| m.tiles()).filter(|t| *t != result.tiles).collect();
+                    result.tiles = others[self.rng.gen_range(0..others.len())].clone();
+                    if self.verbose {
+                        println!("{} - blunder!", self.name);
+                    }
+                }
+                let action = ActionMove::new(self.color, &result.tiles);
+                if self.verbose {
+                    println!("{} - depth: {}, score: {}, nodes: {}, threads: {}, moving: {:?}",
+                        self.name, result.depth, result.score, result.nodes, self.threads, action);
+                }
+                Ok(Action::Move(action))
+            }
+            None => Err(format!("{} - Game should be over, I can't find a move to do.", self.name))
+        }
+    }
+
+    fn start_pondering(&mut self, state: &GameState) {
+        if !self.ponder || state.next_color == self.color {
+            return;
+        }
+        // The expected reply of the opponent
+        let replies = CheckersRules::get_legal_movements(&state.board, state.next_color);
+        let expected = self.tt.probe(hash_board(&state.board, state.next_color))
+            .and_then(|entry| entry.best_move_index)
+            .map(|m| m as usize)
+            .filter(|m| *m < replies.len())
+            .unwrap_or(0);
+        let Some(reply) = replies.get(expected) else {
+            return;
+        };
+        let mut board = state.board.clone();
+        board.move_piece(reply).unwrap();
+        if self.pondering.as_ref().is_some_and(|p| p.board.tiles == board.tiles) {
+            return;
+        }
+        self.stop_pondering();
+
+        let context = self.context();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let (thread_board, color) = (board.clone(), self.color);
+        let handle = std::thread::spawn(move || {
+            context.search(&thread_board, color, Instant::now() + PONDER_MAX_TIME, &thread_stop)
+        });
+        self.pondering = Some(Ponder { board, stop, handle });
+    }
+
+    fn stop_pondering(&mut self) {
+        if let Some(ponder) = self.pondering.take() {
+            ponder.stop.store(true, Ordering::Relaxed);
+            let _ = ponder.handle.join();
+        }
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.stop_pondering();
+        self.rng = StdRng::seed_from_u64(seed);
+        // Another noise for the evaluations
+        self.noise_seed = seed;
+        self.tt.clear();
+    }
+
+    fn get_level(&self) -> Option<BotLevel> {
+        self.level
+    }
+}
+
+impl Drop for PlayerBotSearch {
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_finds_winning_jump() {
+        // Black can take the last red piece
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[9] = TileState::BlackMan;
+        board.tiles[6] = TileState::BlackMan;
+        board.tiles[13] = TileState::RedMan;
+        board.tiles[31] = TileState::BlackKnight;
+
+        let mut player = PlayerBotSearch::new("search", Color::Black);
+        player.set_max_depth(4);
+        let result = player.search(&board, Color::Black).unwrap();
+        assert_eq!(result.tiles, vec![9, 16]);
+
+        // Nothing to play
+        board.tiles.fill(TileState::Empty);
+        assert!(player.search(&board, Color::Black).is_none());
+    }
+
+    #[test]
+    fn test_search_without_time() {
+        // Depth 1 is searched even when the search is stopped before it starts
+        let board = CheckersBoard::new();
+        let mut player = PlayerBotSearch::new("search", Color::Black);
+        player.set_max_depth(1);
+        let depth_1 = player.search(&board, Color::Black).unwrap();
+        player.tt.clear();
+        player.set_max_depth(10);
+        let stop = AtomicBool::new(true);
+        let result = player.context().search(&board, Color::Black, Instant::now(), &stop).unwrap();
+        assert_eq!(result.depth, 1);
+        assert_eq!((result.tiles, result.score), (depth_1.tiles, depth_1.score));
+    }
+
+    #[test]
+    fn test_search_multi_threaded() {
+        // Two black knights against one red man: black wins, whatever the number of threads
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[0] = TileState::BlackKnight;
+        board.tiles[1] = TileState::BlackKnight;
+        board.tiles[26] = TileState::RedMan;
+
+        for threads in [1, 4] {
+            let mut player = PlayerBotSearch::new("search", Color::Black);
+            player.set_threads(threads);
+            player.set_max_depth(8);
+            player.set_max_time(Duration::from_secs(30));
+            let result = player.search(&board, Color::Black).unwrap();
+            assert!(CheckersRules::get_legal_movements(&board, Color::Black).iter().any(|m| m.tiles() == result.tiles));
+            assert!(result.score > 0);
+            assert!(result.depth >= 1);
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let mut player = PlayerBotSearch::new("search", Color::Black);
+        player.set_max_depth(4);
+        player.set_max_time(Duration::from_secs(30));
+        let board = CheckersBoard::new();
+        let legal_moves = CheckersRules::get_legal_movements(&board, Color::Black);
+
+        let analysis = player.analyze(&board, Color::Black, 3);
+        assert_eq!(analysis.len(), 3);
+        for (i, line) in analysis.iter().enumerate() {
+            assert_eq!(line.depth, 4);
+            assert_eq!(line.pv[0], line.tiles);
+            assert!(line.pv.len() <= 4);
+            assert!(legal_moves.iter().any(|m| m.tiles() == line.tiles));
+            if i > 0 {
+                assert!(line.score <= analysis[i - 1].score);
+                assert_ne!(line.tiles, analysis[i - 1].tiles);
+            }
+        }
+        // Every move
+        assert_eq!(player.analyze(&board, Color::Black, 100).len(), legal_moves.len());
+        assert!(player.analyze(&board, Color::Black, 0).is_empty());
+
+        // The winning jump first
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[9] = TileState::BlackMan;
+        board.tiles[6] = TileState::BlackMan;
+        board.tiles[13] = TileState::RedMan;
+        board.tiles[31] = TileState::BlackKnight;
+        let analysis = player.analyze(&board, Color::Black, 1);
+        assert_eq!(analysis[0].tiles, vec![9, 16]);
+        assert!(analysis[0].to_notation(Color::Black).starts_with("10x17 (win, depth "));
+    }
+
+    #[test]
+    fn test_pondering() {
+        let mut player = PlayerBotSearch::new("search", Color::Black);
+        player.set_verbose(false);
+        player.set_max_depth(6);
+        player.set_max_time(Duration::from_secs(30));
+        let play = |player: &mut PlayerBotSearch, board: &CheckersBoard| {
+            let state = GameState { board: board.clone(), next_color: Color::Black, turn: 0, draw_offer: None };
+            let legal_moves = CheckersRules::get_legal_movements(board, Color::Black);
+            match player.play_turn(&state, &legal_moves, None) {
+                Ok(Action::Move(action_move)) => {
+                    assert!(legal_moves.iter().any(|m| m.tiles() == action_move.tiles));
+                    let mut board = board.clone();
+                    board.move_piece(&action_move.to_movement()).unwrap();
+                    board
+                }
+                other => panic!("Unexpected action {:?}", other)
+            }
+        };
+        let after_black = play(&mut player, &CheckersBoard::new());
+        let red_turn = GameState { board: after_black.clone(), next_color: Color::Red, turn: 1, draw_offer: None };
+
+        // Off by default
+        player.start_pondering(&red_turn);
+        assert!(!player.is_pondering());
+
+        // The expected reply is played
+        player.set_pondering(true);
+        player.start_pondering(&red_turn);
+        assert!(player.is_pondering());
+        let expected = player.pondering.as_ref().unwrap().board.clone();
+        play(&mut player, &expected);
+        assert!(!player.is_pondering());
+
+        // Another reply
+        player.start_pondering(&red_turn);
+        let red_moves = CheckersRules::get_legal_movements(&after_black, Color::Red);
+        let other = red_moves.iter().map(|m| {
+            let mut board = after_black.clone();
+            board.move_piece(m).unwrap();
+            board
+        }).find(|board| board.tiles != expected.tiles).unwrap();
+        play(&mut player, &other);
+
+        // Not on its own turn, and stopped when switched off
+        player.start_pondering(&GameState { board: expected.clone(), next_color: Color::Black, turn: 2, draw_offer: None });
+        assert!(!player.is_pondering());
+        player.start_pondering(&red_turn);
+        player.set_pondering(false);
+        assert!(!player.is_pondering());
+
+        // The opponent replies at once: the unfinished ponder search is not played, the position is searched
+        player.set_pondering(true);
+        player.start_pondering(&red_turn);
+        let expected = player.pondering.as_ref().unwrap().board.clone();
+        let state = GameState { board: expected.clone(), next_color: Color::Black, turn: 2, draw_offer: None };
+        let legal_moves = CheckersRules::get_legal_movements(&expected, Color::Black);
+        let played = match player.play_turn(&state, &legal_moves, None) {
+            Ok(Action::Move(action_move)) => action_move.tiles,
+            other => panic!("Unexpected action {:?}", other)
+        };
+        assert_eq!(played, player.search(&expected, Color::Black).unwrap().tiles);
+    }
+
+    #[test]
+    fn test_search_levels() {
+        let board = CheckersBoard::new();
+        let legal_moves = CheckersRules::get_legal_movements(&board, Color::Black);
+        let state = GameState { board: board.clone(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let play = |level: BotLevel, seed: u64| {
+            let mut player = PlayerBotSearch::new("search", Color::Black);
+            player.set_verbose(false);
+            player.set_level(level);
+            player.set_seed(seed);
+            assert_eq!(player.get_level(), Some(level));
+            (0..20).map(|_| match player.play_turn(&state, &legal_moves, None) {
+                Ok(Action::Move(action_move)) => action_move.tiles,
+                other => panic!("Unexpected action {:?}", other)
+            }).collect::<Vec<Vec<usize>>>()
+        };
+        // Same seed, same moves
+        let beginner = play(BotLevel::Beginner, 3);
+        assert_eq!(play(BotLevel::Beginner, 3), beginner);
+        // The beginner blunders, not the advanced player
+        assert!(beginner.iter().any(|tiles| *tiles != beginner[0]));
+        let advanced = play(BotLevel::Advanced, 3);
+        assert!(advanced.iter().all(|tiles| *tiles == advanced[0]));
+        assert_eq!(PlayerBotSearch::new("search", Color::Black).get_level(), None);
+    }
+
+    #[test]
+    fn test_search_endgame_db() {
+        use sm_checkers_base::endgame_db::*;
+        let dir = std::env::temp_dir().join("sm_checkers_test_search_endgame_db");
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = generate(2, &dir, &mut |_, _| {}).unwrap();
+        let mut player = PlayerBotSearch::new("search", Color::Black);
+        player.set_max_depth(2);
+        player.set_endgame_db(Arc::new(EndgameProbe::open(&dir, 1).unwrap()));
+
+        // A man against a king: the score of the search is the value of the database
+        let index = MaterialIndex::new(Material::new(1, 0, 0, 1));
+        let mut found = vec![];
+        for i in 0..index.size() {
+            let board = index.board(i);
+            let value = db.lookup(&board, Color::Black).unwrap();
+            if found.contains(&value) || CheckersRules::get_legal_movements(&board, Color::Black).len() < 2 {
+                continue;
+            }
+            found.push(value);
+            let score = player.search(&board, Color::Black).unwrap().score;
+            match value {
+                DbValue::Win => assert!(score > DB_WIN_SCORE / 2),
+                DbValue::Loss => assert!(score < -DB_WIN_SCORE / 2),
+                DbValue::Draw => assert_eq!(score, 0)
+            }
+        }
+        assert_eq!(found.len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
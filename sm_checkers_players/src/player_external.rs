@@ -0,0 +1,140 @@
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::player_colors::Color;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::engine_protocol::*;
+
+// Player running in another process: an engine written by another team, in any
+// language, speaking the line protocol of engine_protocol.rs on its stdin/stdout.
+// Its stderr is shared with the game, for its logs.
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Added to the time left on the clock: the time to send the move back
+const REPLY_MARGIN: Duration = Duration::from_secs(1);
+const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct PlayerExternal {
+    name: String,
+    color: Color,
+    engine_name: String,
+    child: Child,
+    connection: EngineConnection,
+    reply_timeout: Duration     // Without a clock
+}
+
+impl PlayerExternal {
+    // Starts the engine, and checks that it speaks the protocol
+    pub fn new(name_in: &str, color_in: Color, program: &str, args: &[String]) -> Result<Self, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{} - Failed to start {}: {}", name_in, program, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut connection = EngineConnection::new(name_in, Box::new(stdout), Box::new(stdin));
+        let engine_name = match connection.handshake(HANDSHAKE_TIMEOUT) {
+            Ok(engine_name) => engine_name,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+        Ok(PlayerExternal {
+            name: name_in.to_owned(),
+            color: color_in,
+            engine_name,
+            child,
+            connection,
+            reply_timeout: DEFAULT_REPLY_TIMEOUT
+        })
+    }
+
+    // Name given by the engine itself
+    pub fn get_engine_name(&self) -> String {
+        self.engine_name.clone()
+    }
+
+    // Longest wait for a move when the game has no clock
+    pub fn set_reply_timeout(&mut self, timeout: Duration) {
+        self.reply_timeout = timeout;
+    }
+}
+
+impl Player for PlayerExternal {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        let timeout = time_left.map_or(self.reply_timeout, |t| t + REPLY_MARGIN);
+        self.connection.play_turn(self.color, state, legal_moves, time_left, timeout)
+    }
+
+    fn answer_draw_offer(&mut self, state: &GameState) -> Action {
+        self.connection.answer_draw_offer(state, self.reply_timeout)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        // A dead engine is noticed at its next move
+        let _ = self.connection.send(&EngineRequest::Seed(seed));
+    }
+}
+
+impl Drop for PlayerExternal {
+    fn drop(&mut self) {
+        let _ = self.connection.send(&EngineRequest::Quit);
+        // Leave it some time to exit by itself
+        for _ in 0..20 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use sm_checkers_base::checkers_rules::CheckersRules;
+
+    #[test]
+    fn test_engine_not_found() {
+        assert!(PlayerExternal::new("NONE", Color::Black, "./no-such-engine", &[]).is_err());
+    }
+
+    // A scripted engine: handshake, ignores the seed, one illegal move, then a legal one
+    #[cfg(unix)]
+    #[test]
+    fn test_scripted_engine() {
+        let script = "read l; echo name SCRIPT; read l; read l; echo info thinking; echo move 9-15; read l; echo move 9-13; read l".to_string();
+        let mut player = PlayerExternal::new("EXTERNAL", Color::Black, "sh", &["-c".to_string(), script]).unwrap();
+        assert_eq!(player.get_engine_name(), "SCRIPT");
+        player.set_seed(1);
+
+        let state = GameState { board: CheckersBoard::new(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Black);
+        assert!(player.play_turn(&state, &legal_moves, None).is_err());
+        assert_eq!(player.play_turn(&state, &legal_moves, Some(Duration::from_secs(1))), Ok(Action::Move(ActionMove::new(Color::Black, &vec![8, 12]))));
+        // The engine does not answer anymore
+        player.set_reply_timeout(Duration::from_millis(100));
+        assert!(player.play_turn(&state, &legal_moves, None).is_err());
+    }
+}
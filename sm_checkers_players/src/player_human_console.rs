@@ -1,87 +1,160 @@
 use std::io;
-use std::io::Write;
-use sm_checkers_base::checkers_board::*;
+use std::io::{BufRead, BufReader, Write};
+use std::time::Duration;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::notation::*;
 use sm_checkers_base::player_colors::Color;
 
 use crate::player_trait::*;
 use crate::player_actions::*;
 
-#[derive(Clone)]
+// Human player typing commands in the console.
+// The moves are written in the standard notation (see notation.rs): "11-15", "22x15x8",
+// or only the destination when a single move reaches it: "15".
+// An invalid command is explained, and the player is asked again.
+
+const HELP: &str = "Commands:
+  11-15, 22x15x8  play a move (the destination alone is enough when a single move reaches it)
+  moves           list the legal moves
+  hint            ask for a good move
+  undo            take back your last move
+  draw            offer a draw
+  resign          resign the game
+  quit            leave the game
+  help            this help";
+
+// What the player typed
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Action(Action),
+    ListMoves,
+    Help
+}
+
+fn parse_command(line: &str, color: Color, legal_moves: &[Box<dyn Movement>]) -> Result<Command, String> {
+    match line.trim().to_lowercase().as_str() {
+        "" => Err("Please type a move or a command, \"help\" lists them".into()),
+        "moves" | "m" => Ok(Command::ListMoves),
+        "help" | "?" => Ok(Command::Help),
+        "hint" | "h" => Ok(Command::Action(Action::Hint)),
+        "undo" | "u" => Ok(Command::Action(Action::Undo)),
+        "draw" | "d" => Ok(Command::Action(Action::OfferDraw)),
+        "resign" | "r" => Ok(Command::Action(Action::Resign)),
+        "quit" | "q" => Ok(Command::Action(Action::Quit)),
+        text => {
+            let tiles = find_movement(text, legal_moves)?;
+            Ok(Command::Action(Action::Move(ActionMove::new(color, &tiles))))
+        }
+    }
+}
+
 pub struct PlayerHumanConsole {
     name: String,
-    color: Color
+    color: Color,
+    input: Box<dyn BufRead>
 }
 
 impl PlayerHumanConsole {
     pub fn new(name_in: & str, color_in: Color) -> Self {
+        PlayerHumanConsole::with_input(name_in, color_in, Box::new(BufReader::new(io::stdin())))
+    }
+
+    // Reads the commands from any input, for the tests
+    pub fn with_input(name_in: & str, color_in: Color, input: Box<dyn BufRead>) -> Self {
         PlayerHumanConsole {
             name: name_in.to_owned(),
-            color: color_in
+            color: color_in,
+            input
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => Err(format!("{} - End of the input", self.name)),
+            Ok(_) => Ok(line),
+            Err(e) => Err(format!("{} - Failed to read the input: {}", self.name, e))
         }
     }
 }
 
 impl Player for PlayerHumanConsole {
     fn get_color(&self) -> Color {
-        self.color.clone()
+        self.color
     }
     fn get_name(&self) -> String {
         self.name.clone()
     }
-    fn play_turn(&self) -> Box<dyn Action> {
-        println!("{} - Please write move (ex: \"m 1, 2\") or quit with letter \"q\":", self.name);
-
-        let mut input = String::new();
-        io::stdout().flush().unwrap();
-        input.clear();
-        io::stdin().read_line(&mut input).expect("Failed to read Action from Player");
-        
+    fn play_turn(&mut self, _state: &GameState, legal_moves: &[Box<dyn Movement>], _time_left: Option<Duration>) -> Result<Action, String> {
         loop {
-            match input.to_lowercase().chars().next() {
-                Some('q') => {
-                    println!("{} - Received quit", self.name);
-                    let action = ActionQuit::new();
-                    return Box::new(action);
+            println!("{} - Your move (ex: \"11-15\"), or \"help\":", self.name);
+            let line = self.read_line()?;
+            match parse_command(&line, self.color, legal_moves) {
+                Ok(Command::Action(action)) => return Ok(action),
+                Ok(Command::ListMoves) => {
+                    let moves: Vec<String> = legal_moves.iter().map(|m| movement_to_string(m.as_ref())).collect();
+                    println!("Legal moves: {}", moves.join(", "));
                 }
-                // Matching:
-                // m 1, 2
-                // m 8, 12
-                // m 28, 32
-                // m 1, 10, 19, 26
-                // Convert to move(1, vec[2])...
-                Some('m') => {
-                    while input.chars().next().unwrap().is_digit(10) == false {
-                       input.remove(0) ;
-                    }
-                    println!("{} - Received move: {:?}", self.name, input);
-                    let tiles_str: Vec<&str> = input.split(',').collect();
-                    let mut tiles_idx: Vec<usize> = Vec::new();
-                    for s in tiles_str {
-                        if s.trim().is_empty() {
-                            continue;
-                        }
-                        let val = s.trim().parse::<usize>().unwrap();
-                        if (val == 0) || (val > 32) {
-                            println!("Invalid command: {}", input.trim());
-                        }
-                        tiles_idx.push(val-1);
-                    }
-    
-                    println!(" --> Received move: {:?}", tiles_idx);
-                    let action = ActionMove::new(self.color, &tiles_idx);
-                    return Box::new(action);
-                }
-                _ => {
-                    println!("Invalid command: {}", input.trim());
-               }            
+                Ok(Command::Help) => println!("{}", HELP),
+                Err(e) => println!("{}", e)
+            }
+        }
+    }
+
+    fn answer_draw_offer(&mut self, _state: &GameState) -> Action {
+        loop {
+            println!("{} - Your opponent offers a draw, do you accept? (yes/no)", self.name);
+            match self.read_line() {
+                Ok(line) => match line.trim().to_lowercase().as_str() {
+                    "y" | "yes" => return Action::AcceptDraw,
+                    "n" | "no" => return Action::DeclineDraw,
+                    _ => {}
+                },
+                Err(_) => return Action::DeclineDraw
             }
         }
     }
 }
 
-impl GameBoardObserver for PlayerHumanConsole {
-    fn update(&mut self, _bc: &CheckersBoard) {
-        //println!("{} - Received new board", self.name);
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use sm_checkers_base::checkers_rules::CheckersRules;
+
+    fn player_with_input(text: &str) -> PlayerHumanConsole {
+        PlayerHumanConsole::with_input("HUMAN", Color::Black, Box::new(Cursor::new(text.to_string())))
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_play_turn() {
+        let state = GameState { board: CheckersBoard::new(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Black);
+
+        // Garbage, out of range square, illegal and ambiguous moves, then a legal move
+        let mut player = player_with_input("hello\n\n11-99\n11-17\n14\nmoves\nhelp\n9-13\n");
+        assert_eq!(player.play_turn(&state, &legal_moves, None), Ok(Action::Move(ActionMove::new(Color::Black, &vec![8, 12]))));
+        // The input is over
+        assert!(player.play_turn(&state, &legal_moves, None).is_err());
+
+        // Destination only
+        let mut player = player_with_input("13\n");
+        assert_eq!(player.play_turn(&state, &legal_moves, None), Ok(Action::Move(ActionMove::new(Color::Black, &vec![8, 12]))));
+
+        let mut player = player_with_input("undo\nHINT\nresign\ndraw\nquit\n");
+        for action in [Action::Undo, Action::Hint, Action::Resign, Action::OfferDraw, Action::Quit] {
+            assert_eq!(player.play_turn(&state, &legal_moves, None), Ok(action));
+        }
+
+        let mut player = player_with_input("maybe\nyes\nno\n");
+        assert_eq!(player.answer_draw_offer(&state), Action::AcceptDraw);
+        assert_eq!(player.answer_draw_offer(&state), Action::DeclineDraw);
+        assert_eq!(player.answer_draw_offer(&state), Action::DeclineDraw);
+    }
+}
@@ -0,0 +1,359 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use sm_checkers_base::checkers_board::*;
+use sm_checkers_base::checkers_rules::*;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::player_colors::*;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::evaluator::*;
+
+// Monte Carlo Tree Search player, with the UCT selection rule.
+//
+// Each iteration goes down the tree, choosing the child with the best
+//   wins / visits + exploration * sqrt(ln(parent visits) / visits)
+// adds one new node, plays a rollout until the end of the game, and adds
+// the result (1 win, 0.5 draw, 0 loss) to every node of the path.
+// The most visited move of the root is played.
+
+const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+const DEFAULT_MAX_ROLLOUT_PLIES: usize = 150;
+// Probability of a random move in the evaluator-guided rollouts
+const ROLLOUT_EPSILON: f64 = 0.1;
+// A rollout stopped by the ply limit is scored 0.5 + 0.5 * tanh(value / ROLLOUT_VALUE_SCALE)
+const ROLLOUT_VALUE_SCALE: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MctsBudget {
+    Iterations(usize),
+    Time(Duration)
+}
+
+#[derive(Clone)]
+pub enum RolloutPolicy {
+    Random,                         // The moves of PlayerBotRandom
+    Evaluator(Arc<dyn Evaluator>)   // The best move for the evaluator, or a random one with a probability of ROLLOUT_EPSILON
+}
+
+#[derive(Debug, Clone)]
+pub struct MctsResult {
+    pub tiles: Vec<usize>,
+    pub visits: u32,
+    pub win_rate: f64,
+    pub iterations: usize
+}
+
+struct Node {
+    board: CheckersBoard,
+    to_move: Color,
+    tiles: Vec<usize>,          // Move from the parent
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Box<dyn Movement>>,
+    visits: u32,
+    wins: f64                   // For the player who played the move of the node
+}
+
+pub struct PlayerMcts {
+    name: String,
+    color: Color,
+    budget: MctsBudget,
+    exploration: f64,
+    max_rollout_plies: usize,
+    rollout_policy: RolloutPolicy,
+    rng: StdRng,
+    verbose: bool
+}
+
+impl PlayerMcts {
+    pub fn new(name_in: & str, color_in: Color) -> Self {
+        PlayerMcts {
+            name: name_in.to_owned(),
+            color: color_in,
+            budget: MctsBudget::Iterations(2000),
+            exploration: DEFAULT_EXPLORATION,
+            max_rollout_plies: DEFAULT_MAX_ROLLOUT_PLIES,
+            rollout_policy: RolloutPolicy::Random,
+            rng: StdRng::from_entropy(),
+            verbose: true
+        }
+    }
+
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    pub fn set_budget(&mut self, budget: MctsBudget) {
+        self.budget = budget;
+    }
+
+    pub fn set_exploration(&mut self, exploration: f64) {
+        self.exploration = exploration;
+    }
+
+    pub fn set_max_rollout_plies(&mut self, max_rollout_plies: usize) {
+        self.max_rollout_plies = max_rollout_plies;
+    }
+
+    pub fn set_rollout_policy(&mut self, rollout_policy: RolloutPolicy) {
+        self.rollout_policy = rollout_policy;
+    }
+
+    pub fn search(&mut self, board: &CheckersBoard, color: Color) -> Option<MctsResult> {
+        self.search_with_budget(board, color, self.budget)
+    }
+
+    fn search_with_budget(&mut self, board: &CheckersBoard, color: Color, budget: MctsBudget) -> Option<MctsResult> {
+        let rng = &mut self.rng;
+        let mut tree = vec![Node {
+            board: board.clone(),
+            to_move: color,
+            tiles: vec![],
+            parent: None,
+            children: vec![],
+            untried: CheckersRules::get_legal_movements(board, color),
+            visits: 0,
+            wins: 0.0
+        }];
+        if tree[0].untried.is_empty() {
+            return None;
+        }
+
+        let start = Instant::now();
+        let mut iterations = 0;
+        loop {
+            // At least one iteration, so that the root has a child to play
+            let done = match budget {
+                MctsBudget::Iterations(n) => iterations >= n.max(1),
+                MctsBudget::Time(t) => iterations > 0 && start.elapsed() >= t
+            };
+            if done {
+                break;
+            }
+            iterations += 1;
+
+            // Selection
+            let mut node = 0;
+            while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+                node = select_child(&tree, node, self.exploration);
+            }
+
+            // Expansion
+            if !tree[node].untried.is_empty() {
+                let choice = rng.gen_range(0..tree[node].untried.len());
+                let movement = tree[node].untried.swap_remove(choice);
+                let mut child_board = tree[node].board.clone();
+                child_board.move_piece(&movement).unwrap();
+                let child_color = opposite_color(tree[node].to_move);
+                let untried = CheckersRules::get_legal_movements(&child_board, child_color);
+                tree.push(Node {
+                    board: child_board,
+                    to_move: child_color,
+                    tiles: movement.tiles(),
+                    parent: Some(node),
+                    children: vec![],
+                    untried,
+                    visits: 0,
+                    wins: 0.0
+                });
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                node = child;
+            }
+
+            // Simulation: value of the game for the player to move at the node
+            let value = rollout(&self.rollout_policy, self.max_rollout_plies, &tree[node].board, tree[node].to_move, rng);
+
+            // Backpropagation: the wins of a node are for the player who moved into it
+            let mut value_for_mover = 1.0 - value;
+            let mut current = Some(node);
+            while let Some(n) = current {
+                tree[n].visits += 1;
+                tree[n].wins += value_for_mover;
+                value_for_mover = 1.0 - value_for_mover;
+                current = tree[n].parent;
+            }
+        }
+
+        let best = *tree[0].children.iter().max_by_key(|c| tree[**c].visits).unwrap();
+        Some(MctsResult {
+            tiles: tree[best].tiles.clone(),
+            visits: tree[best].visits,
+            win_rate: tree[best].wins / tree[best].visits as f64,
+            iterations
+        })
+    }
+}
+
+fn select_child(tree: &[Node], node: usize, exploration: f64) -> usize {
+    let log_visits = (tree[node].visits as f64).ln();
+    let uct = |c: usize| {
+        let child = &tree[c];
+        child.wins / child.visits as f64 + exploration * (log_visits / child.visits as f64).sqrt()
+    };
+    *tree[node].children.iter().max_by(|a, b| uct(**a).total_cmp(&uct(**b))).unwrap()
+}
+
+// Plays the game until the end, or max_rollout_plies.
+// Returns 1 if the player to move wins, 0 if they lose.
+fn rollout(policy: &RolloutPolicy, max_plies: usize, board: &CheckersBoard, color: Color, rng: &mut StdRng) -> f64 {
+    let mut board = board.clone();
+    let mut to_move = color;
+    for _ in 0..max_plies {
+        let tiles = match policy {
+            RolloutPolicy::Random => choose_legal_random_move(&board, to_move, rng),
+            RolloutPolicy::Evaluator(evaluator) => choose_evaluator_move(&board, to_move, evaluator.as_ref(), rng)
+        };
+        match tiles {
+            Some(tiles) => {
+                board.move_piece(&ActionMove::new(to_move, &tiles).to_movement()).unwrap();
+                to_move = opposite_color(to_move);
+            }
+            None => {
+                // The player to move has lost
+                return if to_move == color { 0.0 } else { 1.0 };
+            }
+        }
+    }
+
+    match policy {
+        RolloutPolicy::Random => 0.5,
+        RolloutPolicy::Evaluator(evaluator) => 0.5 + 0.5 * (evaluator.evaluate(&board, color) / ROLLOUT_VALUE_SCALE).tanh()
+    }
+}
+
+// A whole multi-jump, not only its first jump
+fn choose_legal_random_move(board: &CheckersBoard, color: Color, rng: &mut StdRng) -> Option<Vec<usize>> {
+    let movements = CheckersRules::get_legal_movements(board, color);
+    if movements.is_empty() {
+        return None;
+    }
+    Some(movements[rng.gen_range(0..movements.len())].tiles())
+}
+
+fn choose_evaluator_move(board: &CheckersBoard, color: Color, evaluator: &dyn Evaluator, rng: &mut StdRng) -> Option<Vec<usize>> {
+    let movements = CheckersRules::get_legal_movements(board, color);
+    if movements.is_empty() {
+        return None;
+    }
+    if rng.gen::<f64>() < ROLLOUT_EPSILON {
+        return Some(movements[rng.gen_range(0..movements.len())].tiles());
+    }
+    movements.iter()
+        .map(|m| {
+            let mut next_board = board.clone();
+            next_board.move_piece(m).unwrap();
+            (evaluator.evaluate(&next_board, color), m)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, m)| m.tiles())
+}
+
+impl Player for PlayerMcts {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn play_turn(&mut self, state: &GameState, _legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        // Never more than the time left on the clock
+        let budget = match (self.budget, time_left) {
+            (MctsBudget::Time(t), Some(left)) => MctsBudget::Time(t.min(left)),
+            (budget, _) => budget
+        };
+        match self.search_with_budget(&state.board, self.color, budget) {
+            Some(result) => {
+                let action = ActionMove::new(self.color, &result.tiles);
+                if self.verbose {
+                    println!("{} - iterations: {}, visits: {}, win rate: {:.3}, moving: {:?}",
+                        self.name, result.iterations, result.visits, result.win_rate, action);
+                }
+                Ok(Action::Move(action))
+            }
+            None => Err(format!("{} - Game should be over, I can't find a move to do.", self.name))
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcts_winning_position() {
+        // Black must take the red man
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[9] = TileState::BlackMan;
+        board.tiles[6] = TileState::BlackMan;
+        board.tiles[13] = TileState::RedMan;
+        board.tiles[31] = TileState::BlackKnight;
+
+        let mut player = PlayerMcts::new("MCTS", Color::Black);
+        player.set_seed(3);
+        player.set_budget(MctsBudget::Iterations(200));
+        let result = player.search(&board, Color::Black).unwrap();
+        assert_eq!(result.tiles, vec![9, 16]);
+        assert_eq!(result.iterations, 200);
+        assert_eq!(result.win_rate, 1.0);
+
+        // Two black knights against one red man: black wins more rollouts than red
+        board.tiles.fill(TileState::Empty);
+        board.tiles[0] = TileState::BlackKnight;
+        board.tiles[1] = TileState::BlackKnight;
+        board.tiles[26] = TileState::RedMan;
+        let result = player.search(&board, Color::Black).unwrap();
+        assert!(result.win_rate > 0.5);
+
+        // Nothing to play
+        board.tiles.fill(TileState::Empty);
+        assert!(player.search(&board, Color::Black).is_none());
+    }
+
+    #[test]
+    fn test_rollout_multi_jump() {
+        // Black takes both red men with one multi-jump, and wins
+        let (board, color) = sm_checkers_base::notation::fen_to_board("B:W6,15:B1").unwrap();
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            assert_eq!(rollout(&RolloutPolicy::Random, DEFAULT_MAX_ROLLOUT_PLIES, &board, color, &mut rng), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_mcts_is_reproducible() {
+        let board = CheckersBoard::new();
+        let play = |policy: RolloutPolicy| {
+            let mut player = PlayerMcts::new("MCTS", Color::Black);
+            player.set_seed(7);
+            player.set_budget(MctsBudget::Iterations(300));
+            player.set_rollout_policy(policy);
+            player.search(&board, Color::Black).unwrap().tiles
+        };
+        assert_eq!(play(RolloutPolicy::Random), play(RolloutPolicy::Random));
+        let material: Arc<dyn Evaluator> = Arc::new(MaterialEvaluator::new());
+        assert_eq!(play(RolloutPolicy::Evaluator(material.clone())), play(RolloutPolicy::Evaluator(material)));
+
+        // The time budget makes at least one iteration
+        let mut player = PlayerMcts::new("MCTS", Color::Red);
+        player.set_budget(MctsBudget::Time(Duration::from_millis(20)));
+        assert!(player.search(&board, Color::Red).unwrap().iterations >= 1);
+        // And so does a budget of 0 iterations
+        player.set_budget(MctsBudget::Iterations(0));
+        assert_eq!(player.search(&board, Color::Red).unwrap().iterations, 1);
+    }
+}
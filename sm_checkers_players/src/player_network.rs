@@ -0,0 +1,172 @@
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::player_colors::Color;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::engine_protocol::*;
+
+// Remote player, connected over TCP: a person playing on another computer with
+// "sm-checkers connect", or any engine speaking the protocol of engine_protocol.rs.
+// The moves received are checked against the legal moves, a closed connection
+// is an error at the next request.
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Added to the time left on the clock: the time to send the move back
+const REPLY_MARGIN: Duration = Duration::from_secs(2);
+// People need time to think
+const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+pub struct PlayerNetwork {
+    name: String,
+    color: Color,
+    remote_name: String,
+    stream: TcpStream,
+    connection: EngineConnection,
+    reply_timeout: Duration     // Without a clock
+}
+
+impl PlayerNetwork {
+    // Waits for the remote player on an address like "0.0.0.0:7878"
+    pub fn listen<A: ToSocketAddrs>(name_in: &str, color_in: Color, address: A) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{} - Failed to listen: {}", name_in, e))?;
+        PlayerNetwork::accept(name_in, color_in, &listener)
+    }
+
+    pub fn accept(name_in: &str, color_in: Color, listener: &TcpListener) -> Result<Self, String> {
+        let (stream, _) = listener.accept().map_err(|e| format!("{} - Failed to accept a connection: {}", name_in, e))?;
+        PlayerNetwork::with_stream(name_in, color_in, stream)
+    }
+
+    pub fn with_stream(name_in: &str, color_in: Color, stream: TcpStream) -> Result<Self, String> {
+        let _ = stream.set_nodelay(true);
+        let reader = stream.try_clone().map_err(|e| format!("{} - {}", name_in, e))?;
+        let writer = stream.try_clone().map_err(|e| format!("{} - {}", name_in, e))?;
+        let mut connection = EngineConnection::new(name_in, Box::new(reader), Box::new(writer));
+        let remote_name = connection.handshake(HANDSHAKE_TIMEOUT)?;
+        Ok(PlayerNetwork {
+            name: name_in.to_owned(),
+            color: color_in,
+            remote_name,
+            stream,
+            connection,
+            reply_timeout: DEFAULT_REPLY_TIMEOUT
+        })
+    }
+
+    // Name given by the remote player
+    pub fn get_remote_name(&self) -> String {
+        self.remote_name.clone()
+    }
+
+    // Longest wait for a move when the game has no clock
+    pub fn set_reply_timeout(&mut self, timeout: Duration) {
+        self.reply_timeout = timeout;
+    }
+}
+
+impl Player for PlayerNetwork {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        let timeout = time_left.map_or(self.reply_timeout, |t| t + REPLY_MARGIN);
+        self.connection.play_turn(self.color, state, legal_moves, time_left, timeout)
+    }
+
+    fn answer_draw_offer(&mut self, state: &GameState) -> Action {
+        self.connection.answer_draw_offer(state, self.reply_timeout)
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        let _ = self.connection.send(&EngineRequest::Seed(seed));
+    }
+}
+
+impl Drop for PlayerNetwork {
+    fn drop(&mut self) {
+        let _ = self.connection.send(&EngineRequest::Quit);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use std::thread;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use sm_checkers_base::checkers_rules::CheckersRules;
+    use crate::player_bot_random::PlayerBotRandom;
+
+    #[test]
+    fn test_network_player() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The remote side: a random bot, served like an engine
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let mut input = BufReader::new(stream.try_clone().unwrap());
+            let mut output = stream;
+            let mut new_player = |color: Color| -> Box<dyn Player> {
+                let mut player = PlayerBotRandom::new("REMOTE", color);
+                player.set_verbose(false);
+                Box::new(player)
+            };
+            serve_engine("REMOTE RANDOM", &mut input, &mut output, &mut new_player)
+        });
+
+        let mut player = PlayerNetwork::accept("NETWORK", Color::Red, &listener).unwrap();
+        assert_eq!(player.get_remote_name(), "REMOTE RANDOM");
+        player.set_seed(3);
+
+        let mut board = CheckersBoard::new();
+        board.move_piece(&CheckersRules::get_legal_movements(&board, Color::Black)[0]).unwrap();
+        let state = GameState { board, next_color: Color::Red, turn: 1, draw_offer: None };
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Red);
+        match player.play_turn(&state, &legal_moves, Some(Duration::from_secs(5))) {
+            Ok(Action::Move(action_move)) => assert!(legal_moves.iter().any(|m| m.tiles() == action_move.tiles)),
+            other => panic!("Unexpected action {:?}", other)
+        }
+
+        // The game is over: the remote side stops
+        drop(player);
+        assert_eq!(client.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // Answers the handshake, then leaves
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let mut input = BufReader::new(stream.try_clone().unwrap());
+            let mut output = stream;
+            let mut line = String::new();
+            std::io::BufRead::read_line(&mut input, &mut line).unwrap();
+            std::io::Write::write_all(&mut output, b"name LEAVER\n").unwrap();
+        });
+        let mut player = PlayerNetwork::accept("NETWORK", Color::Black, &listener).unwrap();
+        client.join().unwrap();
+
+        let state = GameState { board: CheckersBoard::new(), next_color: Color::Black, turn: 0, draw_offer: None };
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, Color::Black);
+        // Noticed at once, without waiting for the timeout
+        player.set_reply_timeout(Duration::from_secs(60));
+        let start = std::time::Instant::now();
+        assert!(player.play_turn(&state, &legal_moves, None).is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
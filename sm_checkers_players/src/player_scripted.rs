@@ -0,0 +1,147 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::time::Duration;
+use sm_checkers_base::movements::*;
+use sm_checkers_base::notation::*;
+use sm_checkers_base::pdn::*;
+use sm_checkers_base::player_colors::Color;
+
+use crate::player_trait::*;
+use crate::player_actions::*;
+use crate::bot_level::BotLevel;
+
+// Player replaying the moves of a script: the moves of a whole game, both colors,
+// in the standard notation ("11-15", "22x15x8"). The move played at a turn is
+// the move of the script at this turn, so undos replay the same moves.
+// After the script, the fallback player takes over, if any.
+//
+// To reproduce a bug report, set up an opening before an engine plays, or
+// test the game without a human at the keyboard.
+
+pub struct PlayerScripted {
+    name: String,
+    color: Color,
+    script: Vec<String>,
+    fallback: Option<Rc<RefCell<dyn Player>>>
+}
+
+impl PlayerScripted {
+    pub fn new(name_in: &str, color_in: Color, script: &[&str]) -> Self {
+        PlayerScripted {
+            name: name_in.to_owned(),
+            color: color_in,
+            script: script.iter().map(|m| m.to_string()).collect(),
+            fallback: None
+        }
+    }
+
+    // The moves of a game of a PDN file
+    pub fn from_pdn_game(name_in: &str, color_in: Color, game: &PdnGame) -> Self {
+        PlayerScripted {
+            name: name_in.to_owned(),
+            color: color_in,
+            script: game.moves.clone(),
+            fallback: None
+        }
+    }
+
+    // Plays when the script is over
+    pub fn set_fallback(&mut self, fallback: Rc<RefCell<dyn Player>>) {
+        self.fallback = Some(fallback);
+    }
+
+    pub fn get_script_len(&self) -> usize {
+        self.script.len()
+    }
+}
+
+impl Player for PlayerScripted {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        match self.script.get(state.turn) {
+            Some(text) => {
+                let tiles = find_movement(text, legal_moves)
+                    .map_err(|e| format!("{} - Move {} of the script: {}", self.name, state.turn + 1, e))?;
+                Ok(Action::Move(ActionMove::new(self.color, &tiles)))
+            }
+            None => match &self.fallback {
+                Some(fallback) => fallback.borrow_mut().play_turn(state, legal_moves, time_left),
+                None => Err(format!("{} - The script is over after {} moves", self.name, self.script.len()))
+            }
+        }
+    }
+
+    fn answer_draw_offer(&mut self, state: &GameState) -> Action {
+        match &self.fallback {
+            Some(fallback) if state.turn >= self.script.len() => fallback.borrow_mut().answer_draw_offer(state),
+            _ => Action::DeclineDraw
+        }
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        if let Some(fallback) = &self.fallback {
+            fallback.borrow_mut().set_seed(seed);
+        }
+    }
+
+    fn start_pondering(&mut self, state: &GameState) {
+        match &self.fallback {
+            Some(fallback) if state.turn + 1 >= self.script.len() => fallback.borrow_mut().start_pondering(state),
+            _ => {}
+        }
+    }
+
+    fn stop_pondering(&mut self) {
+        if let Some(fallback) = &self.fallback {
+            fallback.borrow_mut().stop_pondering();
+        }
+    }
+
+    fn get_level(&self) -> Option<BotLevel> {
+        self.fallback.as_ref().and_then(|fallback| fallback.borrow().get_level())
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::checkers_board::CheckersBoard;
+    use sm_checkers_base::checkers_rules::CheckersRules;
+    use crate::player_bot_random::PlayerBotRandom;
+
+    fn state_at(turn: usize, next_color: Color) -> GameState {
+        GameState { board: CheckersBoard::new(), next_color, turn, draw_offer: None }
+    }
+
+    #[test]
+    fn test_scripted_player() {
+        let board = CheckersBoard::new();
+        let black_moves = CheckersRules::get_legal_movements(&board, Color::Black);
+
+        let mut player = PlayerScripted::new("SCRIPT", Color::Black, &["11-15", "23-19", "99-1"]);
+        assert_eq!(player.play_turn(&state_at(0, Color::Black), &black_moves, None), Ok(Action::Move(ActionMove::new(Color::Black, &vec![10, 14]))));
+        // Illegal in the position
+        assert!(player.play_turn(&state_at(1, Color::Black), &black_moves, None).is_err());
+        assert!(player.play_turn(&state_at(2, Color::Black), &black_moves, None).is_err());
+        // Over, without a fallback
+        assert!(player.play_turn(&state_at(3, Color::Black), &black_moves, None).is_err());
+
+        let mut random = PlayerBotRandom::new("RANDOM", Color::Black);
+        random.set_verbose(false);
+        player.set_fallback(Rc::new(RefCell::new(random)));
+        match player.play_turn(&state_at(3, Color::Black), &black_moves, None) {
+            Ok(Action::Move(action_move)) => assert!(black_moves.iter().any(|m| m.tiles() == action_move.tiles)),
+            other => panic!("Unexpected action {:?}", other)
+        }
+    }
+}
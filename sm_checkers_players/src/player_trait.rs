@@ -1,13 +1,48 @@
+use std::time::Duration;
+use sm_checkers_base::checkers_board::CheckersBoard;
+use sm_checkers_base::movements::Movement;
 use sm_checkers_base::player_colors::Color;
 use crate::player_actions::Action;
+use crate::bot_level::BotLevel;
+
+// What a player receives when it has to play
+#[derive(Debug, Clone)]
+pub struct GameState {
+    pub board: CheckersBoard,
+    pub next_color: Color,      // The color of the player that has to play
+    pub turn: usize,            // Number of moves already played
+    pub draw_offer: Option<Color>   // The color offering a draw, waiting for an answer
+}
 
 pub trait Player {
     fn get_name(&self) -> String;
     fn get_color(&self) -> Color;
 
-    // Returns a Action: 
-    // The implementation of the play_turn should create a Action and move the ownership of the Action to the caller
-    // In Rust, this is automatic when return a local variable
-    fn play_turn(&self) -> Box<dyn Action>;
-}
+    // Returns the Action of the player, usually a Action::Move
+    //
+    // legal_moves: the moves of CheckersRules::get_legal_movements for the state, never empty
+    // time_left: the time left on the clock of the player, None without a clock
+    // Err: the player is unable to play, it loses the game
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String>;
+
+    // The opponent offers a draw (state.draw_offer): AcceptDraw or DeclineDraw
+    fn answer_draw_offer(&mut self, _state: &GameState) -> Action {
+        Action::DeclineDraw
+    }
 
+    // Players making random choices draw them from a generator seeded here:
+    // same seed, same moves, so that a game can be replayed
+    fn set_seed(&mut self, _seed: u64) {}
+
+    // The opponent has to play (state.next_color): a player may think in the
+    // background until its next play_turn. Called again when the position changes.
+    fn start_pondering(&mut self, _state: &GameState) {}
+
+    // The game does not go on: the background thinking is useless
+    fn stop_pondering(&mut self) {}
+
+    // The strength level of a bot, if it has one
+    fn get_level(&self) -> Option<BotLevel> {
+        None
+    }
+}
@@ -0,0 +1,94 @@
+use sm_checkers_base::checkers_board::*;
+use sm_checkers_base::game_result::GameResult;
+use sm_checkers_base::player_colors::*;
+
+use crate::evaluator::*;
+
+// Temporal-difference learning, TD(lambda), as used by Samuel's and Tesauro's programs.
+//
+// For the positions b0, b1, ..., bT of a game, seen by one player:
+//   delta_t = V(b_t+1) - V(b_t)                 (V(b_T+1) is the final value of the game)
+//   e_t     = lambda * e_t-1 + grad V(b_t)      (eligibility trace)
+//   w      <- w + alpha * sum(delta_t * e_t)
+// The update is applied once at the end of the game (offline TD(lambda)).
+// With lambda = 0, each position only learns from the next one; with lambda = 1,
+// every position learns from the final result.
+
+pub struct TdLambdaLearner {
+    pub lambda: f64,
+    pub alpha: f64      // Learning rate
+}
+
+impl TdLambdaLearner {
+    pub fn new(lambda: f64, alpha: f64) -> TdLambdaLearner {
+        if !(0.0..=1.0).contains(&lambda) {
+            panic!("TdLambdaLearner: lambda must be between 0 and 1, received {}", lambda);
+        }
+        TdLambdaLearner { lambda, alpha }
+    }
+
+    // Returns the mean squared temporal difference of the game, before the update
+    pub fn learn_from_game(&self, evaluator: &mut dyn DifferentiableEvaluator, positions: &[CheckersBoard], color: Color, result: &GameResult) -> f64 {
+        if positions.is_empty() {
+            return 0.0;
+        }
+
+        let values: Vec<f64> = positions.iter().map(|b| evaluator.evaluate(b, color)).collect();
+        let gradients: Vec<Vec<f64>> = positions.iter().map(|b| evaluator.get_gradient(b, color)).collect();
+        let nb_parameters = gradients[0].len();
+
+        // Like the LMS rule of PlayerBotAI, the step is divided by the squared norm of the
+        // gradients, so that the same alpha works whatever the scale of the features
+        let mean_norm = gradients.iter().map(|g| g.iter().map(|x| x * x).sum::<f64>()).sum::<f64>() / gradients.len() as f64;
+        let step = self.alpha / mean_norm.max(1.0);
+
+        let mut trace = vec![0.0; nb_parameters];
+        let mut update = vec![0.0; nb_parameters];
+        let mut squared_delta_sum = 0.0;
+        for t in 0..positions.len() {
+            let next_value = match values.get(t + 1) {
+                Some(v) => *v,
+                None => result.get_value(color)
+            };
+            let delta = next_value - values[t];
+            squared_delta_sum += delta * delta;
+            for i in 0..nb_parameters {
+                trace[i] = self.lambda * trace[i] + gradients[t][i];
+                update[i] += step * delta * trace[i];
+            }
+        }
+        evaluator.apply_update(&update);
+        squared_delta_sum / positions.len() as f64
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learn_from_game() {
+        let mut board = CheckersBoard::new();
+        board.tiles.fill(TileState::Empty);
+        board.tiles[20] = TileState::BlackKnight;
+        board.tiles[21] = TileState::BlackKnight;
+        board.tiles[30] = TileState::RedMan;
+        let mut positions = vec![board.clone()];
+        board.tiles[30] = TileState::Empty;
+        positions.push(board.clone());
+
+        for lambda in [0.0, 0.7, 1.0] {
+            let mut evaluator = WeightedFeaturesEvaluator::new(&[0.0; NB_WEIGHTS]);
+            let learner = TdLambdaLearner::new(lambda, 0.1);
+            let error = learner.learn_from_game(&mut evaluator, &positions, Color::Black, &GameResult::Win(Color::Black));
+            assert!(error > 0.0);
+            // The winning positions are worth more after the update
+            assert!(evaluator.evaluate(&positions[1], Color::Black) > 0.0);
+            assert!(evaluator.evaluate(&positions[1], Color::Red) < evaluator.evaluate(&positions[1], Color::Black));
+        }
+    }
+}
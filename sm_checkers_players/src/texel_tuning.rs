@@ -0,0 +1,213 @@
+use sm_checkers_base::checkers_board::CheckersBoard;
+use sm_checkers_base::checkers_rules::CheckersRules;
+use sm_checkers_base::game_result::GameResult;
+use sm_checkers_base::movements::Jump;
+use sm_checkers_base::notation::{fen_to_board, find_movement};
+use sm_checkers_base::pdn::PdnGame;
+use sm_checkers_base::player_colors::*;
+
+use crate::evaluator::*;
+
+// Offline tuning of the weights of WeightedFeaturesEvaluator with the Texel method:
+// the quiet positions of games with a result are collected, then the weights
+// minimise the mean squared error between sigmoid(K * evaluation) and the
+// result of the game (1 win, 0.5 draw, 0 loss for the player to move).
+// K is fitted first, with the starting weights, then each weight is moved by
+// a step as long as the error decreases, the step is halved when no weight moves.
+// Everything is deterministic: the same games give the same weights.
+
+const INITIAL_STEP: f64 = 0.1;
+const MIN_STEP: f64 = 0.001;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningPosition {
+    pub features: [f64; NB_WEIGHTS],    // For the player to move
+    pub result: f64                     // Of the game, for the player to move
+}
+
+pub fn sigmoid(value: f64, k: f64) -> f64 {
+    1.0 / (1.0 + (-k * value).exp())
+}
+
+pub struct TexelTuner {
+    skip_plies: usize,      // Opening plies not collected, played from memory rather than evaluated
+    positions: Vec<TuningPosition>
+}
+
+impl TexelTuner {
+    pub fn new(skip_plies: usize) -> Self {
+        TexelTuner { skip_plies, positions: vec![] }
+    }
+
+    pub fn get_positions(&self) -> &[TuningPosition] {
+        &self.positions
+    }
+
+    // Adds the quiet positions of a game, those where the player to move cannot jump.
+    // Returns the number of positions added, Err without a result or with an illegal move
+    // (nothing is added then).
+    pub fn add_game(&mut self, game: &PdnGame) -> Result<usize, String> {
+        let result = game.game_result().ok_or("The game has no result")?;
+        let (mut board, mut color) = match game.tag("FEN") {
+            Some(fen) => fen_to_board(fen)?,
+            None => (CheckersBoard::new(), Color::Black)
+        };
+        let mut positions = vec![];
+        for (ply, text) in game.moves.iter().enumerate() {
+            let legal_moves = CheckersRules::get_legal_movements(&board, color);
+            let tiles = find_movement(text, &legal_moves).map_err(|e| format!("Move {}: {}", ply + 1, e))?;
+            let quiet = !legal_moves.iter().any(|m| m.as_any().downcast_ref::<Jump>().is_some());
+            if quiet && ply >= self.skip_plies {
+                let value = match result {
+                    GameResult::Win(winner) if winner == color => 1.0,
+                    GameResult::Win(_) => 0.0,
+                    GameResult::Draw => 0.5
+                };
+                positions.push(TuningPosition { features: WeightedFeaturesEvaluator::get_features(&board, color), result: value });
+            }
+
+            let movement = legal_moves.iter().find(|m| m.tiles() == tiles).unwrap();
+            board.move_piece(movement)?;
+            color = opposite_color(color);
+        }
+        let added = positions.len();
+        self.positions.append(&mut positions);
+        Ok(added)
+    }
+
+    // Returns the number of games added, and the errors of the others
+    pub fn add_games(&mut self, games: &[PdnGame]) -> (usize, Vec<String>) {
+        let mut added = 0;
+        let mut errors = vec![];
+        for (index, game) in games.iter().enumerate() {
+            match self.add_game(game) {
+                Ok(_) => added += 1,
+                Err(e) => errors.push(format!("Game {}: {}", index + 1, e))
+            }
+        }
+        (added, errors)
+    }
+
+    pub fn mean_error(&self, weights: &[f64], k: f64) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.positions.iter().map(|p| {
+            let value: f64 = weights.iter().zip(p.features.iter()).map(|(w, x)| w * x).sum();
+            let error = p.result - sigmoid(value, k);
+            error * error
+        }).sum();
+        sum / self.positions.len() as f64
+    }
+
+    // The K giving the smallest error with these weights, searched between 0.01 and 10
+    pub fn fit_scale(&self, weights: &[f64]) -> f64 {
+        // Ternary search on ln(K), the error having a single minimum
+        let (mut low, mut high) = (0.01f64.ln(), 10.0f64.ln());
+        for _ in 0..60 {
+            let a = low + (high - low) / 3.0;
+            let b = high - (high - low) / 3.0;
+            if self.mean_error(weights, a.exp()) <= self.mean_error(weights, b.exp()) {
+                high = b;
+            }
+            else {
+                low = a;
+            }
+        }
+        ((low + high) / 2.0).exp()
+    }
+
+    // Local search from the given weights, for at most max_passes passes over the weights.
+    // report(pass, step, error) is called after each pass.
+    pub fn tune(&self, weights: &[f64], k: f64, max_passes: usize, report: &mut dyn FnMut(usize, f64, f64)) -> Vec<f64> {
+        let mut weights = weights.to_vec();
+        let mut best_error = self.mean_error(&weights, k);
+        let mut step = INITIAL_STEP;
+        for pass in 1..=max_passes {
+            let mut improved = false;
+            for i in 0..weights.len() {
+                let weight = weights[i];
+                for candidate in [weight + step, weight - step] {
+                    weights[i] = candidate;
+                    let error = self.mean_error(&weights, k);
+                    if error < best_error {
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                    weights[i] = weight;
+                }
+            }
+            report(pass, step, best_error);
+            if !improved {
+                step /= 2.0;
+                if step < MIN_STEP {
+                    break;
+                }
+            }
+        }
+        weights
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::checkers_board::TileState;
+    use sm_checkers_base::pdn::parse_pdn;
+
+    #[test]
+    fn test_add_games() {
+        let games = parse_pdn(r#"
+[Result "1-0"] 1. 11-15 23-19 2. 8-11 22-17 1-0
+[Result "0-1"] 1. 11-15 22-18 2. 15x22 0-1
+[Result "1/2-1/2"] [FEN "W:W18:B14"] 1. 18x9 1/2-1/2
+1. 11-15 23-19 *
+[Result "1-0"] 1. 11-15 24-28 1-0
+"#).unwrap();
+        let mut tuner = TexelTuner::new(1);
+        let (added, errors) = tuner.add_games(&games);
+        assert_eq!(added, 3);
+        assert_eq!(errors.len(), 2);
+        // The first ply of each game is skipped, and 15x22 is not quiet
+        let results: Vec<f64> = tuner.get_positions().iter().map(|p| p.result).collect();
+        assert_eq!(results, vec![0.0, 1.0, 0.0, 1.0]);
+
+        assert_eq!(sigmoid(0.0, 1.0), 0.5);
+        assert!((tuner.mean_error(&[0.0; NB_WEIGHTS], 1.0) - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tune() {
+        // The player with more pieces wins, the evaluation must learn it
+        let mut tuner = TexelTuner::new(0);
+        for extra in 0..8 {
+            let mut board = CheckersBoard::new();
+            board.tiles[20 + extra % 4 + 4 * (extra / 4)] = TileState::Empty;
+            for color in [Color::Black, Color::Red] {
+                let result = if color == Color::Black { 1.0 } else { 0.0 };
+                tuner.positions.push(TuningPosition { features: WeightedFeaturesEvaluator::get_features(&board, color), result });
+            }
+        }
+        tuner.positions.push(TuningPosition { features: WeightedFeaturesEvaluator::get_features(&CheckersBoard::new(), Color::Black), result: 0.5 });
+
+        let start = [0.0; NB_WEIGHTS];
+        let k = tuner.fit_scale(&start);
+        assert!((0.01..=10.0).contains(&k));
+        let mut passes = 0;
+        let weights = tuner.tune(&start, 1.0, 20, &mut |pass, _, _| passes = pass);
+        assert!(passes > 0 && passes <= 20);
+        assert!(tuner.mean_error(&weights, 1.0) < tuner.mean_error(&start, 1.0) / 2.0);
+        let evaluator = WeightedFeaturesEvaluator::new(&weights);
+        let mut board = CheckersBoard::new();
+        board.tiles[21] = TileState::Empty;
+        assert!(evaluator.evaluate(&board, Color::Black) > 0.0);
+        assert!(evaluator.evaluate(&board, Color::Red) < 0.0);
+        // Deterministic
+        assert_eq!(tuner.tune(&start, 1.0, 20, &mut |_, _, _| {}), weights);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Transposition table shared by all the search threads.
+//
+// Each slot holds two atomics: the data and the position hash XORed with the data.
+// A slot that was half-written by another thread fails the key check on probe
+// and is simply treated as a miss, so no lock is needed (the "lockless hashing" trick).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,  // The score is at least this value (beta cutoff)
+    Upper   // The score is at most this value (no move raised alpha)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TtEntry {
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub best_move_index: Option<u8>
+}
+
+impl TtEntry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1u64,
+            Bound::Lower => 2u64,
+            Bound::Upper => 3u64
+        };
+        let best_move = self.best_move_index.map_or(0xFF, |m| m as u64);
+        (self.score as u32 as u64) | ((self.depth as u64) << 32) | (bound << 40) | (best_move << 48)
+    }
+
+    fn unpack(data: u64) -> Option<TtEntry> {
+        let bound = match (data >> 40) & 0xFF {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None
+        };
+        let best_move = ((data >> 48) & 0xFF) as u8;
+        Some(TtEntry {
+            score: data as u32 as i32,
+            depth: ((data >> 32) & 0xFF) as u8,
+            bound,
+            best_move_index: if best_move == 0xFF { None } else { Some(best_move) }
+        })
+    }
+}
+
+struct TtSlot {
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+pub struct TranspositionTable {
+    slots: Vec<TtSlot>
+}
+
+const SLOT_SIZE: usize = std::mem::size_of::<TtSlot>();
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        // Round down to a power of two so that the index is a simple mask
+        let wanted = (size_mb.max(1) * 1024 * 1024) / SLOT_SIZE;
+        let nb_slots = 1usize << (usize::BITS - 1 - wanted.leading_zeros());
+        let mut slots = Vec::with_capacity(nb_slots);
+        for _ in 0..nb_slots {
+            slots.push(TtSlot { key: AtomicU64::new(0), data: AtomicU64::new(0) });
+        }
+        TranspositionTable { slots }
+    }
+
+    pub fn size_mb(&self) -> usize {
+        (self.slots.len() * SLOT_SIZE) / (1024 * 1024)
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        let slot = &self.slots[(hash as usize) & (self.slots.len() - 1)];
+        let data = slot.data.load(Ordering::Relaxed);
+        if slot.key.load(Ordering::Relaxed) ^ data != hash {
+            return None;
+        }
+        TtEntry::unpack(data)
+    }
+
+    pub fn store(&self, hash: u64, entry: TtEntry) {
+        let slot = &self.slots[(hash as usize) & (self.slots.len() - 1)];
+        // Keep the deeper result when the same position is already stored
+        if let Some(existing) = self.probe(hash) {
+            if existing.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+        let data = entry.pack();
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.size_mb(), 1);
+        assert!(tt.probe(0x1234).is_none());
+
+        let entry = TtEntry { score: -250, depth: 7, bound: Bound::Lower, best_move_index: Some(3) };
+        tt.store(0x1234, entry);
+        assert_eq!(tt.probe(0x1234), Some(entry));
+
+        // A shallower, non exact result does not replace a deeper one
+        tt.store(0x1234, TtEntry { score: 10, depth: 2, bound: Bound::Upper, best_move_index: None });
+        assert_eq!(tt.probe(0x1234), Some(entry));
+
+        tt.clear();
+        assert!(tt.probe(0x1234).is_none());
+    }
+}
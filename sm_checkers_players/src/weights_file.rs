@@ -0,0 +1,89 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+use crate::evaluator::*;
+
+// Learned weights of a WeightedFeaturesEvaluator, saved as JSON.
+// The feature names are saved too, so that a file can be read by a human
+// and compared with another one.
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LearningMetadata {
+    pub eta: f64,
+    #[serde(default)]
+    pub td_lambda: Option<f64>,     // None: LMS rule, Some(lambda): TD(lambda)
+    pub games: usize,
+    pub mean_squared_error: f64     // Of the last game learned
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightsFile {
+    pub feature_set_version: u32,
+    pub features: Vec<String>,
+    pub weights: Vec<f64>,
+    pub learning: LearningMetadata
+}
+
+impl WeightsFile {
+    pub fn new(weights: &[f64], learning: LearningMetadata) -> WeightsFile {
+        WeightsFile {
+            feature_set_version: FEATURE_SET_VERSION,
+            features: FEATURE_NAMES.iter().map(|f| f.to_string()).collect(),
+            weights: weights.to_vec(),
+            learning
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| format!("Unable to serialize the weights: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<WeightsFile, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        let file: WeightsFile = serde_json::from_str(&json).map_err(|e| format!("Invalid weights file {}: {}", path, e))?;
+        if file.feature_set_version != FEATURE_SET_VERSION {
+            return Err(format!("{} uses the feature set version {}, expected version {}", path, file.feature_set_version, FEATURE_SET_VERSION));
+        }
+        if file.weights.len() != NB_WEIGHTS {
+            return Err(format!("{} has {} weights, expected {}", path, file.weights.len(), NB_WEIGHTS));
+        }
+        if let Some(lambda) = file.learning.td_lambda.filter(|l| !(0.0..=1.0).contains(l)) {
+            return Err(format!("{} has the TD lambda {}, expected a value between 0 and 1", path, lambda));
+        }
+        Ok(file)
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("sm_checkers_test_weights.json");
+        let path = path.to_str().unwrap();
+
+        let learning = LearningMetadata { eta: 0.1, td_lambda: Some(0.7), games: 42, mean_squared_error: 12.5 };
+        let file = WeightsFile::new(&[0.25; NB_WEIGHTS], learning);
+        file.save(path).unwrap();
+        assert_eq!(WeightsFile::load(path).unwrap(), file);
+
+        let mut bad_lambda = file.clone();
+        bad_lambda.learning.td_lambda = Some(2.0);
+        bad_lambda.save(path).unwrap();
+        assert!(WeightsFile::load(path).is_err());
+
+        let mut old_file = file.clone();
+        old_file.feature_set_version = 1;
+        old_file.save(path).unwrap();
+        assert!(WeightsFile::load(path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+        assert!(WeightsFile::load(path).is_err());
+    }
+}
@@ -1,16 +1,29 @@
 
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
 
 use sm_checkers_base::checkers_board::*;
 use sm_checkers_base::checkers_rules::*;
-use sm_checkers_base::player_colors::Color;
-use sm_checkers_players::player_actions::ActionMove;
+use sm_checkers_base::movements::Movement;
+use sm_checkers_base::player_colors::*;
+use sm_checkers_base::game_result::GameResult;
+use sm_checkers_players::player_actions::*;
+use sm_checkers_players::player_bot_search::{AnalysisLine, PlayerBotSearch, DEFAULT_HASH_SIZE_MB};
+use sm_checkers_players::transposition_table::TranspositionTable;
+use sm_checkers_players::player_trait::GameState;
+
+// Thinking time of the engine answering Action::Hint
+pub const HINT_TIME: Duration = Duration::from_millis(500);
+// Number of moves suggested by a hint
+pub const HINT_LINES: usize = 3;
 
 
 // Define the Subject trait
 pub trait Subject {
     fn register_observer(&mut self, bo: Rc<RefCell<dyn GameBoardObserver>>);
+    #[allow(dead_code)]
     fn remove_observer(&mut self, bo: Rc<RefCell<dyn GameBoardObserver>>);
     fn notify_observers(&self);
 }
@@ -38,27 +51,179 @@ impl Subject for CheckersGame {
 }
 
 
+// The engine answering Action::Hint. Its transposition table is allocated by
+// the first hint, and kept for the next ones that start from what it searched.
+#[derive(Default)]
+pub struct HintEngine {
+    tt: Option<Arc<TranspositionTable>>
+}
+
+impl HintEngine {
+    pub fn new() -> Self {
+        HintEngine { tt: None }
+    }
+
+    // The best moves of color, the best first, at most HINT_LINES of them
+    pub fn analyze(&mut self, board: &CheckersBoard, color: Color) -> Vec<AnalysisLine> {
+        let tt = self.tt.get_or_insert_with(|| Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE_MB)));
+        let mut engine = PlayerBotSearch::with_transposition_table("HINT", color, tt.clone());
+        engine.set_verbose(false);
+        engine.set_max_time(HINT_TIME);
+        engine.analyze(board, color, HINT_LINES)
+    }
+}
+
+
+// What the game controller did with an action
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionOutcome {
+    Moved,                  // The turn of the opponent
+    GameOver(GameResult),
+    DrawOffered,            // The opponent must answer
+    DrawDeclined,           // The player that offered the draw must play
+    Undone(usize),          // Number of moves taken back, the same player plays again
+    Hint(Vec<AnalysisLine>) // The best moves first, the same player plays again
+}
+
 pub struct CheckersGame {
     observers: Vec<Rc<RefCell<dyn GameBoardObserver>>>,
-    game_board: CheckersBoard
+    game_board: CheckersBoard,
+    next_color: Color,
+    turn: usize,
+    history: Vec<CheckersBoard>,        // Board before each move, for Undo
+    draw_offer: Option<Color>,
+    draw_declined_turn: Option<usize>,  // Only one draw offer per turn
+    result: Option<GameResult>,
+    hint_engine: HintEngine
 }
 
 impl CheckersGame {
     pub fn new() -> Self {
         CheckersGame {
         observers: Vec::new(),
-        game_board: CheckersBoard::new()
+        game_board: CheckersBoard::new(),
+        next_color: Color::Black,
+        turn: 0,
+        history: Vec::new(),
+        draw_offer: None,
+        draw_declined_turn: None,
+        result: None,
+        hint_engine: HintEngine::new()
         }
     }
 
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    // The color that must act: the side to move, or the opponent of a draw offer
+    pub fn get_color_to_act(&self) -> Color {
+        match self.draw_offer {
+            Some(color) => opposite_color(color),
+            None => self.next_color
+        }
+    }
+
+    // Handles the action of a player
+    pub fn apply_action(&mut self, color: Color, action: &Action) -> Result<ActionOutcome, String> {
+        if self.result.is_some() {
+            return Err("The game is over.".into());
+        }
+        if color != self.get_color_to_act() {
+            return Err(format!("It is not the turn of the {:?} player.", color));
+        }
+        if self.draw_offer.is_some() && !matches!(action, Action::AcceptDraw | Action::DeclineDraw | Action::Resign | Action::Quit) {
+            return Err("Answer the draw offer first.".into());
+        }
+
+        match action {
+            Action::Move(action_move) => {
+                self.move_piece(action_move)?;
+                Ok(match self.result {
+                    Some(result) => ActionOutcome::GameOver(result),
+                    None => ActionOutcome::Moved
+                })
+            }
+            Action::Quit | Action::Resign => {
+                self.draw_offer = None;
+                self.result = Some(GameResult::Win(opposite_color(color)));
+                Ok(ActionOutcome::GameOver(GameResult::Win(opposite_color(color))))
+            }
+            Action::OfferDraw => {
+                if self.draw_declined_turn == Some(self.turn) {
+                    return Err("A draw was already declined this turn.".into());
+                }
+                self.draw_offer = Some(color);
+                Ok(ActionOutcome::DrawOffered)
+            }
+            Action::AcceptDraw | Action::DeclineDraw => {
+                if self.draw_offer.is_none() {
+                    return Err("There is no draw offer to answer.".into());
+                }
+                self.draw_offer = None;
+                if *action == Action::AcceptDraw {
+                    self.result = Some(GameResult::Draw);
+                    return Ok(ActionOutcome::GameOver(GameResult::Draw));
+                }
+                self.draw_declined_turn = Some(self.turn);
+                Ok(ActionOutcome::DrawDeclined)
+            }
+            Action::Undo => {
+                // The last move of the player, and the answer of the opponent
+                if self.history.len() < 2 {
+                    return Err("There is no move of yours to undo.".into());
+                }
+                for _ in 0..2 {
+                    self.game_board = self.history.pop().unwrap();
+                }
+                self.turn -= 2;
+                self.draw_declined_turn = None;
+                self.notify_observers();
+                Ok(ActionOutcome::Undone(2))
+            }
+            Action::Hint => {
+                let analysis = self.hint_engine.analyze(&self.game_board, color);
+                if analysis.is_empty() {
+                    return Err("There is no move to play.".into());
+                }
+                Ok(ActionOutcome::Hint(analysis))
+            }
+        }
+    }
+
+    // What the next player receives
+    pub fn get_state(&self) -> GameState {
+        GameState {
+            board: self.game_board.clone(),
+            next_color: self.next_color,
+            turn: self.turn,
+            draw_offer: self.draw_offer
+        }
+    }
+
+    pub fn get_legal_movements(&self) -> Vec<Box<dyn Movement>> {
+        CheckersRules::get_legal_movements(&self.game_board, self.next_color)
+    }
+
     pub fn is_game_over(&self, next_player_color: Color) -> bool {
         return self.game_board.is_game_over(next_player_color);
     }
 
     pub fn move_piece(&mut self, action: &ActionMove) -> Result<(), String> {
-
+        if self.result.is_some() {
+            return Err("The game is over.".into());
+        }
+        if action.player_color != self.next_color {
+            return Err(format!("It is not the turn of the {:?} player.", action.player_color));
+        }
         self.is_move_valid(action)?;
+        self.history.push(self.game_board.clone());
         self.game_board.move_piece(&action.to_movement()).unwrap();
+        self.next_color = opposite_color(action.player_color);
+        self.turn += 1;
+        if self.game_board.is_game_over(self.next_color) {
+            self.result = Some(GameResult::Win(action.player_color));
+        }
         self.notify_observers();
         return Ok(());
     }
@@ -97,6 +262,9 @@ impl CheckersGame {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sm_checkers_base::notation::board_to_fen;
+    use sm_checkers_players::*;
+    use crate::game_runner::play_game;
 
     #[test]
     fn test_is_move_invalid() {
@@ -249,6 +417,8 @@ mod tests {
         assert!(game.move_piece(&action).is_ok());
         assert!(game.game_board.tiles[28] == TileState::BlackKnight);
 
+        // Red had no piece: the game was over
+        game.result = None;
         game.game_board.tiles.fill(TileState::Empty);
         game.game_board.tiles[5] = TileState::RedMan;
         let action = ActionMove::new(Color::Red, &vec![5, 1]);
@@ -281,4 +451,146 @@ mod tests {
         assert!(game.is_move_valid(&action).is_ok());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_game_state() {
+        let mut game = CheckersGame::new();
+        let state = game.get_state();
+        assert_eq!(state.next_color, Color::Black);
+        assert_eq!(state.turn, 0);
+        assert_eq!(game.get_legal_movements().len(), 7);
+
+        // Red can't play first
+        assert!(game.move_piece(&ActionMove::new(Color::Red, &vec![20, 16])).is_err());
+        assert!(game.move_piece(&ActionMove::new(Color::Black, &vec![8, 12])).is_ok());
+        let state = game.get_state();
+        assert_eq!(state.next_color, Color::Red);
+        assert_eq!(state.turn, 1);
+        assert_eq!(state.board.tiles[12], TileState::BlackMan);
+        assert!(game.move_piece(&ActionMove::new(Color::Black, &vec![9, 13])).is_err());
+    }
+
+    #[test]
+    fn test_apply_action() {
+        let black_move = |tiles: Vec<usize>| Action::Move(ActionMove::new(Color::Black, &tiles));
+        let red_move = |tiles: Vec<usize>| Action::Move(ActionMove::new(Color::Red, &tiles));
+
+        // Moves, and undo
+        let mut game = CheckersGame::new();
+        assert!(game.apply_action(Color::Black, &Action::Undo).is_err());
+        assert_eq!(game.apply_action(Color::Black, &black_move(vec![8, 12])), Ok(ActionOutcome::Moved));
+        assert!(game.apply_action(Color::Black, &black_move(vec![9, 13])).is_err());
+        assert_eq!(game.apply_action(Color::Red, &red_move(vec![20, 16])), Ok(ActionOutcome::Moved));
+        assert_eq!(game.apply_action(Color::Black, &Action::Undo), Ok(ActionOutcome::Undone(2)));
+        assert_eq!(game.get_state().turn, 0);
+        assert_eq!(game.game_board.tiles, CheckersBoard::new().tiles);
+        assert!(matches!(game.apply_action(Color::Black, &Action::Hint), Ok(ActionOutcome::Hint(lines)) if lines.len() == HINT_LINES));
+        // The table of the first hint is the one of the next hints
+        let hint_table = game.hint_engine.tt.clone().unwrap();
+        assert!(matches!(game.apply_action(Color::Black, &Action::Hint), Ok(ActionOutcome::Hint(_))));
+        assert!(Arc::ptr_eq(&hint_table, game.hint_engine.tt.as_ref().unwrap()));
+
+        // Draw offers
+        assert_eq!(game.apply_action(Color::Black, &Action::OfferDraw), Ok(ActionOutcome::DrawOffered));
+        assert_eq!(game.get_color_to_act(), Color::Red);
+        assert_eq!(game.get_state().draw_offer, Some(Color::Black));
+        assert!(game.apply_action(Color::Black, &black_move(vec![8, 12])).is_err());
+        assert_eq!(game.apply_action(Color::Red, &Action::DeclineDraw), Ok(ActionOutcome::DrawDeclined));
+        assert!(game.apply_action(Color::Black, &Action::OfferDraw).is_err());
+        assert!(game.apply_action(Color::Black, &Action::AcceptDraw).is_err());
+        assert_eq!(game.apply_action(Color::Black, &black_move(vec![8, 12])), Ok(ActionOutcome::Moved));
+        assert_eq!(game.apply_action(Color::Red, &Action::OfferDraw), Ok(ActionOutcome::DrawOffered));
+        assert_eq!(game.apply_action(Color::Black, &Action::AcceptDraw), Ok(ActionOutcome::GameOver(GameResult::Draw)));
+        assert_eq!(game.get_result(), Some(GameResult::Draw));
+        assert!(game.apply_action(Color::Red, &red_move(vec![20, 16])).is_err());
+
+        // Resign and quit
+        let mut game = CheckersGame::new();
+        assert_eq!(game.apply_action(Color::Black, &Action::Resign), Ok(ActionOutcome::GameOver(GameResult::Win(Color::Red))));
+        let mut game = CheckersGame::new();
+        game.apply_action(Color::Black, &black_move(vec![8, 12])).unwrap();
+        assert_eq!(game.apply_action(Color::Red, &Action::Quit), Ok(ActionOutcome::GameOver(GameResult::Win(Color::Black))));
+
+        // The winning move ends the game
+        let mut game = CheckersGame::new();
+        game.game_board.tiles.fill(TileState::Empty);
+        game.game_board.tiles[9] = TileState::BlackMan;
+        game.game_board.tiles[13] = TileState::RedMan;
+        assert_eq!(game.apply_action(Color::Black, &black_move(vec![9, 16])), Ok(ActionOutcome::GameOver(GameResult::Win(Color::Black))));
+    }
+
+    // Games played by scripted players, through the game runner
+    fn scripted_players(black_script: &[&str], red_script: &[&str]) -> Vec<Rc<RefCell<dyn Player>>> {
+        vec![Rc::new(RefCell::new(PlayerScripted::new("BLACK", Color::Black, black_script))),
+             Rc::new(RefCell::new(PlayerScripted::new("RED", Color::Red, red_script)))]
+    }
+
+    #[test]
+    fn test_scripted_games() {
+        let opening = ["11-15", "23-19", "8-11", "22-17", "9-13", "17-14", "10x17", "21x14"];
+
+        // The same script for both players: each one plays the moves of its turns
+        let players = scripted_players(&opening, &opening);
+        let mut game = CheckersGame::new();
+        let record = play_game(&mut game, &players, opening.len(), 0);
+        assert_eq!(record.result, GameResult::Draw);
+        assert_eq!(record.moves.len(), 8);
+        assert_eq!(record.moves[5], (Color::Red, vec![16, 13]));
+        let scripted_moves = record.moves.clone();
+        assert_eq!(board_to_fen(&game.get_state().board, game.get_state().next_color),
+            "B:W14,19,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,11,12,13,15");
+
+        // The script is over: the player without a fallback loses
+        let players = scripted_players(&opening, &opening);
+        let record = play_game(&mut CheckersGame::new(), &players, 100, 0);
+        assert_eq!(record.result, GameResult::Win(Color::Red));
+        assert_eq!(record.moves.len(), 8);
+
+        // An illegal move in the script
+        let players = scripted_players(&["11-15", "23-19", "11-16"], &["11-15", "23-19", "11-16"]);
+        let record = play_game(&mut CheckersGame::new(), &players, 100, 0);
+        assert_eq!(record.result, GameResult::Win(Color::Red));
+        assert_eq!(record.moves.len(), 2);
+
+        // A fallback takes over after the opening
+        let mut black = PlayerScripted::new("BLACK", Color::Black, &opening);
+        let mut red = PlayerScripted::new("RED", Color::Red, &opening);
+        black.set_fallback(Rc::new(RefCell::new(PlayerBotSearch::new("SEARCH", Color::Black))));
+        red.set_fallback(Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", Color::Red))));
+        let players: Vec<Rc<RefCell<dyn Player>>> = vec![Rc::new(RefCell::new(black)), Rc::new(RefCell::new(red))];
+        let record = play_game(&mut CheckersGame::new(), &players, 20, 0);
+        assert_eq!(record.moves.len(), 20);
+        assert_eq!(record.moves[..8], scripted_moves[..]);
+
+        // A winning move from a set up position
+        let mut game = CheckersGame::new();
+        game.game_board.tiles.fill(TileState::Empty);
+        game.game_board.tiles[9] = TileState::BlackMan;
+        game.game_board.tiles[13] = TileState::RedMan;
+        let record = play_game(&mut game, &scripted_players(&["10x17"], &[]), 100, 0);
+        assert_eq!(record.result, GameResult::Win(Color::Black));
+        assert_eq!(record.moves, vec![(Color::Black, vec![9, 16])]);
+    }
+
+    #[test]
+    fn test_scripted_undo() {
+        // After an undo, the scripted players replay the moves of the turns taken back
+        let opening = ["11-15", "23-19", "8-11", "22-17"];
+        let players = scripted_players(&opening, &opening);
+        let mut game = CheckersGame::new();
+        for _ in 0..2 {
+            for player in players.iter() {
+                let legal_moves = game.get_legal_movements();
+                let action = player.borrow_mut().play_turn(&game.get_state(), &legal_moves, None).unwrap();
+                assert_eq!(game.apply_action(player.borrow().get_color(), &action), Ok(ActionOutcome::Moved));
+            }
+        }
+        let fen = board_to_fen(&game.get_state().board, Color::Black);
+        assert_eq!(game.apply_action(Color::Black, &Action::Undo), Ok(ActionOutcome::Undone(2)));
+        for player in players.iter() {
+            let legal_moves = game.get_legal_movements();
+            let action = player.borrow_mut().play_turn(&game.get_state(), &legal_moves, None).unwrap();
+            assert_eq!(game.apply_action(player.borrow().get_color(), &action), Ok(ActionOutcome::Moved));
+        }
+        assert_eq!(board_to_fen(&game.get_state().board, Color::Black), fen);
+    }
+}
@@ -0,0 +1,357 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+
+use sm_checkers_base::Color;
+use sm_checkers_base::CheckersBoard;
+use sm_checkers_base::CheckersRules;
+use sm_checkers_base::GameResult;
+use sm_checkers_base::checkers_board::TileState;
+use sm_checkers_base::player_colors::opposite_color;
+use sm_checkers_base::notation::{board_to_fen, fen_to_board, find_movement};
+use sm_checkers_base::pdn::{load_pdn_file, PdnGame};
+use sm_checkers_players::*;
+use sm_checkers_players::player_bot_search::WIN_SCORE;
+
+use crate::checkers_game::CheckersGame;
+use crate::game_runner::play_game;
+use crate::training::derive_seed;
+
+// Export of positions with their search score and the result of their game,
+// to train models outside of this program. The games are replayed from PDN
+// files, or played by two search bots of the given level.
+//
+// The values are absolute, not relative to the player to move:
+//   tiles:        32 values, tile 0 (square 1) first: 0 empty, 1 black man, 2 black king,
+//                 -1 red man, -2 red king (Black starts on squares 1 to 12)
+//   side to move: 1 Black, -1 Red
+//   result:       1 Black wins, 0 draw, -1 Red wins
+// The score is the one of the search, for the player to move, in hundredths of a man
+// (the position after a forced move is searched instead).
+//
+// CSV: a header line, then "FEN",t1,...,t32,side_to_move,score,result
+// Binary: the magic "SMTD", a version byte, then records of 38 bytes:
+//   32 tiles (i8), side to move (i8), score (i32 little endian), result (i8)
+
+const BINARY_MAGIC: &[u8; 4] = b"SMTD";
+const BINARY_VERSION: u8 = 1;
+pub const BINARY_RECORD_SIZE: usize = 38;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Binary
+}
+
+pub struct ExportOptions {
+    pub pdn_files: Vec<String>,     // Games replayed, none: games played
+    pub games: usize,               // Games played without PDN files
+    pub level: BotLevel,            // Of the players of the played games
+    pub depth: u32,                 // Of the search scoring the positions
+    pub max_turns: usize,           // A played game is a draw after that many turns
+    pub seed: u64,
+    pub format: ExportFormat,
+    pub output: String
+}
+
+impl ExportOptions {
+    pub fn new() -> ExportOptions {
+        ExportOptions {
+            pdn_files: vec![],
+            games: 10,
+            level: BotLevel::Intermediate,
+            depth: 6,
+            max_turns: 200,
+            seed: 0,
+            format: ExportFormat::Csv,
+            output: "positions.csv".to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionRecord {
+    pub board: CheckersBoard,
+    pub color: Color,       // To move
+    pub score: i32,
+    pub result: GameResult
+}
+
+// A game played or replayed, from its start position
+struct ExportGame {
+    board: CheckersBoard,
+    color: Color,
+    moves: Vec<Vec<usize>>,
+    result: GameResult
+}
+
+pub fn encode_tiles(board: &CheckersBoard) -> [i8; 32] {
+    let mut tiles = [0; 32];
+    for (code, tile) in tiles.iter_mut().zip(board.tiles.iter()) {
+        *code = match tile {
+            TileState::Empty => 0,
+            TileState::BlackMan => 1,
+            TileState::BlackKnight => 2,
+            TileState::RedMan => -1,
+            TileState::RedKnight => -2
+        };
+    }
+    tiles
+}
+
+fn color_code(color: Color) -> i8 {
+    if color == Color::Black { 1 } else { -1 }
+}
+
+fn result_code(result: GameResult) -> i8 {
+    match result {
+        GameResult::Win(Color::Black) => 1,
+        GameResult::Win(Color::Red) => -1,
+        GameResult::Draw => 0
+    }
+}
+
+pub fn write_header(out: &mut dyn Write, format: ExportFormat) -> std::io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let tiles: Vec<String> = (1..=32).map(|i| format!("t{}", i)).collect();
+            writeln!(out, "fen,{},side_to_move,score,result", tiles.join(","))
+        }
+        ExportFormat::Binary => {
+            out.write_all(BINARY_MAGIC)?;
+            out.write_all(&[BINARY_VERSION])
+        }
+    }
+}
+
+pub fn write_record(out: &mut dyn Write, record: &PositionRecord, format: ExportFormat) -> std::io::Result<()> {
+    let tiles = encode_tiles(&record.board);
+    match format {
+        ExportFormat::Csv => {
+            let tiles: Vec<String> = tiles.iter().map(|t| t.to_string()).collect();
+            writeln!(out, "\"{}\",{},{},{},{}", board_to_fen(&record.board, record.color), tiles.join(","),
+                color_code(record.color), record.score, result_code(record.result))
+        }
+        ExportFormat::Binary => {
+            let mut bytes = Vec::with_capacity(BINARY_RECORD_SIZE);
+            bytes.extend(tiles.iter().map(|t| *t as u8));
+            bytes.push(color_code(record.color) as u8);
+            bytes.extend_from_slice(&record.score.to_le_bytes());
+            bytes.push(result_code(record.result) as u8);
+            out.write_all(&bytes)
+        }
+    }
+}
+
+// Depth limited only, so that the scores do not depend on the speed of the machine
+fn new_scorer(depth: u32) -> PlayerBotSearch {
+    let mut scorer = PlayerBotSearch::new("SCORER", Color::Black);
+    scorer.set_verbose(false);
+    scorer.set_max_depth(depth);
+    scorer.set_max_time(Duration::from_secs(3600));
+    scorer
+}
+
+// Forced moves in a row are not searched further than this
+const MAX_FORCED_PLIES: usize = 64;
+
+// The search plays a forced move without scoring it: the score of a forced
+// move is the one of the position after it, for the other player
+fn score_position(board: &CheckersBoard, color: Color, scorer: &PlayerBotSearch) -> i32 {
+    let (mut board, mut color) = (board.clone(), color);
+    let mut sign = 1;
+    for ply in 0..MAX_FORCED_PLIES {
+        let legal_moves = CheckersRules::get_legal_movements(&board, color);
+        match legal_moves.len() {
+            0 => return sign * (-WIN_SCORE + ply as i32),
+            1 => {
+                board.move_piece(&legal_moves[0]).unwrap();
+                color = opposite_color(color);
+                sign = -sign;
+            }
+            _ => return sign * scorer.search(&board, color).map(|r| r.score).unwrap_or(0)
+        }
+    }
+    0
+}
+
+// The position before each move, scored by the search
+fn score_positions(game: &ExportGame, scorer: &PlayerBotSearch) -> Result<Vec<PositionRecord>, String> {
+    let (mut board, mut color) = (game.board.clone(), game.color);
+    let mut records = vec![];
+    for tiles in game.moves.iter() {
+        let score = score_position(&board, color, scorer);
+        records.push(PositionRecord { board: board.clone(), color, score, result: game.result });
+        let legal_moves = CheckersRules::get_legal_movements(&board, color);
+        let movement = legal_moves.iter().find(|m| m.tiles() == *tiles).ok_or(format!("Illegal move {:?}", tiles))?;
+        board.move_piece(movement)?;
+        color = opposite_color(color);
+    }
+    Ok(records)
+}
+
+// The start position and the moves of a game with a result
+fn replay_pdn_game(game: &PdnGame) -> Result<ExportGame, String> {
+    let result = game.game_result().ok_or("The game has no result")?;
+    let (start, start_color) = match game.tag("FEN") {
+        Some(fen) => fen_to_board(fen)?,
+        None => (CheckersBoard::new(), Color::Black)
+    };
+    let (mut board, mut color) = (start.clone(), start_color);
+    let mut moves = vec![];
+    for (ply, text) in game.moves.iter().enumerate() {
+        let legal_moves = CheckersRules::get_legal_movements(&board, color);
+        let tiles = find_movement(text, &legal_moves).map_err(|e| format!("Move {}: {}", ply + 1, e))?;
+        let movement = legal_moves.iter().find(|m| m.tiles() == tiles).unwrap();
+        board.move_piece(movement)?;
+        color = opposite_color(color);
+        moves.push(tiles);
+    }
+    Ok(ExportGame { board: start, color: start_color, moves, result })
+}
+
+fn play_export_game(options: &ExportOptions, game_index: usize) -> ExportGame {
+    let new_player = |name: &str, color: Color| -> Rc<RefCell<dyn Player>> {
+        let mut player = PlayerBotSearch::new(name, color);
+        player.set_verbose(false);
+        player.set_level(options.level);
+        Rc::new(RefCell::new(player))
+    };
+    let players = [new_player("BLACK", Color::Black), new_player("RED", Color::Red)];
+    let record = play_game(&mut CheckersGame::new(), &players, options.max_turns, derive_seed(options.seed, 12, game_index));
+    let moves = record.moves.into_iter().map(|(_, tiles)| tiles).collect();
+    ExportGame { board: CheckersBoard::new(), color: Color::Black, moves, result: record.result }
+}
+
+// Returns the number of positions written
+pub fn run_export(options: &ExportOptions) -> Result<usize, String> {
+    let file = File::create(&options.output).map_err(|e| format!("Unable to create {}: {}", options.output, e))?;
+    let mut out = BufWriter::new(file);
+    let write_error = |e: std::io::Error| format!("Unable to write {}: {}", options.output, e);
+    write_header(&mut out, options.format).map_err(write_error)?;
+
+    let scorer = new_scorer(options.depth);
+    let mut positions = 0;
+    let mut export = |game: &ExportGame| -> Result<usize, String> {
+        let records = score_positions(game, &scorer)?;
+        for record in records.iter() {
+            write_record(&mut out, record, options.format).map_err(write_error)?;
+        }
+        positions += records.len();
+        Ok(records.len())
+    };
+
+    if options.pdn_files.is_empty() {
+        println!("Playing {} games at the {} level, seed: {}", options.games, options.level.name(), options.seed);
+        for game_index in 0..options.games {
+            let game = play_export_game(options, game_index);
+            let exported = export(&game)?;
+            println!("Game {}: {:?}, {} positions", game_index + 1, game.result, exported);
+        }
+    }
+    for path in &options.pdn_files {
+        let (mut added, mut skipped) = (0, 0);
+        for (index, game) in load_pdn_file(path)?.iter().enumerate() {
+            match replay_pdn_game(game) {
+                Ok(game) => {
+                    export(&game)?;
+                    added += 1;
+                }
+                Err(e) => {
+                    println!("{}: Game {}: {}", path, index + 1, e);
+                    skipped += 1;
+                }
+            }
+        }
+        println!("{}: {} games exported, {} skipped", path, added, skipped);
+    }
+    out.flush().map_err(write_error)?;
+    println!("{} positions written to {}", positions, options.output);
+    Ok(positions)
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_record() {
+        let record = PositionRecord { board: CheckersBoard::new(), color: Color::Black, score: -25, result: GameResult::Win(Color::Red) };
+        let mut csv = vec![];
+        write_header(&mut csv, ExportFormat::Csv).unwrap();
+        write_record(&mut csv, &record, ExportFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("fen,t1,t2,") && lines[0].ends_with(",t32,side_to_move,score,result"));
+        assert!(lines[1].starts_with("\"B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12\",1,1,"));
+        assert!(lines[1].ends_with(",0,0,0,0,0,0,0,0,-1,-1,-1,-1,-1,-1,-1,-1,-1,-1,-1,-1,1,-25,-1"));
+
+        let mut binary = vec![];
+        write_header(&mut binary, ExportFormat::Binary).unwrap();
+        write_record(&mut binary, &record, ExportFormat::Binary).unwrap();
+        assert_eq!(binary.len(), 5 + BINARY_RECORD_SIZE);
+        assert_eq!(&binary[0..4], b"SMTD");
+        assert_eq!(binary[5] as i8, 1);
+        assert_eq!(binary[5 + 31] as i8, -1);
+        assert_eq!(binary[5 + 32] as i8, 1);
+        assert_eq!(i32::from_le_bytes(binary[5 + 33..5 + 37].try_into().unwrap()), -25);
+        assert_eq!(binary[5 + 37] as i8, -1);
+    }
+
+    #[test]
+    fn test_score_forced_moves() {
+        let scorer = new_scorer(2);
+        // Black has to jump 14x23, and is then a man up
+        let (board, color) = fen_to_board("B:W18,30:B1,14").unwrap();
+        let legal_moves = CheckersRules::get_legal_movements(&board, color);
+        assert_eq!(legal_moves.len(), 1);
+        let score = score_position(&board, color, &scorer);
+        assert!(score > 0);
+        let mut child = board.clone();
+        child.move_piece(&legal_moves[0]).unwrap();
+        assert_eq!(score, -scorer.search(&child, Color::Red).unwrap().score);
+    }
+
+    #[test]
+    fn test_run_export() {
+        let dir = std::env::temp_dir();
+        let pdn = dir.join("sm_checkers_test_export.pdn");
+        std::fs::write(&pdn, "[Result \"1-0\"] 1. 11-15 23-19 2. 8-11 1-0\n1. 9-13 *\n[Result \"0-1\"] [FEN \"W:W18:B14\"] 1. 18x9 0-1\n").unwrap();
+        let mut options = ExportOptions::new();
+        options.pdn_files = vec![pdn.to_str().unwrap().to_string()];
+        options.depth = 2;
+        options.output = dir.join("sm_checkers_test_export.csv").to_str().unwrap().to_string();
+        assert_eq!(run_export(&options), Ok(4));
+        let csv = std::fs::read_to_string(&options.output).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        let last: Vec<&str> = csv.lines().last().unwrap().split(',').collect();
+        assert_eq!(last[0], "\"W:W18:B14\"");
+        // Red has to jump, and then Black cannot move: not a score of 0
+        assert_eq!(last[34].parse::<i32>().unwrap(), WIN_SCORE - 1);
+
+        // Played games, in binary
+        options.pdn_files = vec![];
+        options.games = 1;
+        options.level = BotLevel::Beginner;
+        options.max_turns = 10;
+        options.format = ExportFormat::Binary;
+        options.output = dir.join("sm_checkers_test_export.bin").to_str().unwrap().to_string();
+        assert_eq!(run_export(&options), Ok(10));
+        let binary = std::fs::read(&options.output).unwrap();
+        assert_eq!(binary.len(), 5 + 10 * BINARY_RECORD_SIZE);
+        // Reproducible
+        run_export(&options).unwrap();
+        assert_eq!(std::fs::read(&options.output).unwrap(), binary);
+
+        std::fs::remove_file(&pdn).unwrap();
+        std::fs::remove_file(dir.join("sm_checkers_test_export.csv")).unwrap();
+        std::fs::remove_file(&options.output).unwrap();
+    }
+}
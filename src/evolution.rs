@@ -0,0 +1,178 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use sm_checkers_base::Color;
+use sm_checkers_players::*;
+
+use crate::training::*;
+
+// Evolution of the weights of PlayerBotAI, the genetic algorithm of chapter 9 of
+// Tom Mitchell's "Machine Learning": each generation, the weight vectors of the
+// population play a round robin tournament, the best ones survive and breed
+// the rest of the next generation by crossover and mutation.
+// Every random choice derives from the seed, so a run can be reproduced.
+
+// Probability of a weight of a child to be mutated
+const MUTATION_RATE: f64 = 0.25;
+
+pub struct EvolutionOptions {
+    pub population: usize,
+    pub generations: usize,
+    pub games: usize,           // Games of each pair of the tournament, alternating colors
+    pub survivors: usize,       // Best weights kept for the next generation, the parents of the children
+    pub mutation: f64,          // Maximum change of a mutated weight
+    pub seed: u64,
+    pub eval_games: usize,      // Games of the best weights against PlayerBotRandom, for each report
+    pub max_turns: usize,       // A game is a draw after that many turns
+    pub load: Option<String>,   // Weights file of the first individual, the others are its mutations
+    pub save: Option<String>    // Weights file of the best individual of the last generation
+}
+
+impl EvolutionOptions {
+    pub fn new() -> EvolutionOptions {
+        EvolutionOptions {
+            population: 12,
+            generations: 20,
+            games: 2,
+            survivors: 4,
+            mutation: 0.2,
+            seed: 0,
+            eval_games: 20,
+            max_turns: 200,
+            load: None,
+            save: None
+        }
+    }
+}
+
+fn mutate(weights: &mut [f64], mutation: f64, rng: &mut StdRng) {
+    for w in weights.iter_mut() {
+        if rng.gen_bool(MUTATION_RATE) {
+            *w += rng.gen_range(-mutation..=mutation);
+        }
+    }
+}
+
+// Uniform crossover of two parents, then mutation
+fn breed(a: &[f64], b: &[f64], mutation: f64, rng: &mut StdRng) -> Vec<f64> {
+    let mut child: Vec<f64> = a.iter().zip(b.iter()).map(|(wa, wb)| if rng.gen_bool(0.5) { *wa } else { *wb }).collect();
+    mutate(&mut child, mutation, rng);
+    child
+}
+
+// Points of each individual in a round robin tournament: 1 for a win, 0.5 for a draw
+fn play_tournament(population: &[Vec<f64>], options: &EvolutionOptions, generation: usize) -> Vec<f64> {
+    let mut points = vec![0.0; population.len()];
+    for i in 0..population.len() {
+        for j in i + 1..population.len() {
+            let pair = (generation * population.len() + i) * population.len() + j;
+            let score = compare_weights(&population[i], &population[j], options.games, derive_seed(options.seed, 9, pair), options.max_turns);
+            points[i] += score.wins as f64 + score.draws as f64 / 2.0;
+            points[j] += score.losses as f64 + score.draws as f64 / 2.0;
+        }
+    }
+    points
+}
+
+// Returns the best weights of the last generation
+pub fn run_evolution(options: &EvolutionOptions) -> Result<Vec<f64>, String> {
+    if options.population < 2 {
+        return Err("The population needs at least 2 individuals".into());
+    }
+    if !options.mutation.is_finite() || options.mutation < 0.0 {
+        return Err(format!("The mutation must be a positive number, not {}", options.mutation));
+    }
+    println!("Evolving PlayerBotAI: population {}, {} generations, seed: {}", options.population, options.generations, options.seed);
+
+    // The learning metadata of the loaded file is kept in the saved file
+    let mut player = match &options.load {
+        Some(path) => PlayerBotAI::from_weights_file("EVOLVED", Color::Black, path)?,
+        None => PlayerBotAI::new("EVOLVED", Color::Black)
+    };
+    player.set_verbose(false);
+    let mut rng = StdRng::seed_from_u64(derive_seed(options.seed, 10, 0));
+    let mut population = vec![player.get_evaluator().get_weights().to_vec()];
+    while population.len() < options.population {
+        let mut weights = population[0].clone();
+        mutate(&mut weights, options.mutation, &mut rng);
+        population.push(weights);
+    }
+    let survivors = options.survivors.clamp(1, options.population);
+    let games_per_individual = options.games * (options.population - 1);
+
+    for generation in 0..options.generations.max(1) {
+        let points = play_tournament(&population, options, generation);
+        // Best first, the order of the population breaking the ties
+        let mut ranking: Vec<usize> = (0..population.len()).collect();
+        ranking.sort_by(|a, b| points[*b].total_cmp(&points[*a]));
+        population = ranking.iter().map(|i| population[*i].clone()).collect();
+
+        let mean = points.iter().sum::<f64>() / points.len() as f64;
+        let eval_score = evaluate_against_random(&population[0], options.eval_games, derive_seed(options.seed, 11, generation), options.max_turns);
+        println!("generation: {:>4} | best: {:.1}/{} points | mean: {:.1} | best vs random: {}",
+            generation + 1, points[ranking[0]], games_per_individual, mean, eval_score.percent_string());
+
+        if generation + 1 < options.generations {
+            population.truncate(survivors);
+            while population.len() < options.population {
+                let a = rng.gen_range(0..survivors);
+                let b = rng.gen_range(0..survivors);
+                let child = breed(&population[a], &population[b], options.mutation, &mut rng);
+                population.push(child);
+            }
+        }
+    }
+
+    let weights = population[0].clone();
+    println!("Best weights: {:?}", weights);
+    if let Some(path) = &options.save {
+        player.set_weights(&weights);
+        player.save_weights_file(path)?;
+        println!("Weights saved to {}", path);
+    }
+    Ok(weights)
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_players::evaluator::NB_WEIGHTS;
+
+    #[test]
+    fn test_breed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let a = [1.0; NB_WEIGHTS];
+        let b = [-1.0; NB_WEIGHTS];
+        let child = breed(&a, &b, 0.1, &mut rng);
+        assert_eq!(child.len(), NB_WEIGHTS);
+        assert!(child.iter().all(|w| (w.abs() - 1.0).abs() <= 0.1));
+        assert!(child.iter().any(|w| *w > 0.0) && child.iter().any(|w| *w < 0.0));
+    }
+
+    #[test]
+    fn test_evolution_is_reproducible() {
+        let mut options = EvolutionOptions::new();
+        options.population = 4;
+        options.generations = 2;
+        options.survivors = 2;
+        options.eval_games = 1;
+        options.max_turns = 40;
+        options.seed = 7;
+
+        let weights = run_evolution(&options).unwrap();
+        assert_eq!(weights.len(), NB_WEIGHTS);
+        assert_eq!(weights, run_evolution(&options).unwrap());
+
+        options.mutation = -0.1;
+        assert!(run_evolution(&options).is_err());
+        options.mutation = f64::NAN;
+        assert!(run_evolution(&options).is_err());
+        options.mutation = 0.2;
+        options.population = 1;
+        assert!(run_evolution(&options).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use sm_checkers_base::Color;
+use sm_checkers_base::GameResult;
+use sm_checkers_base::player_colors::opposite_color;
+use sm_checkers_players::*;
+
+use crate::checkers_game::*;
+
+// What is needed to replay a game: the same players, created with the same
+// settings, replay the same moves from the same seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub levels: Vec<Option<BotLevel>>,      // The level of each player, if any
+    pub moves: Vec<(Color, Vec<usize>)>,
+    pub result: GameResult
+}
+
+// Seed of the i-th player of a game
+pub fn player_seed(game_seed: u64, index: usize) -> u64 {
+    game_seed ^ (index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+// Bots may ask for hints, undo or offer draws, but not forever
+const MAX_REQUESTS_PER_TURN: usize = 8;
+
+// Plays a complete game without any interaction, for bots, from the position of the game.
+// The player of the color to move plays, each player is seeded from the seed of the game.
+// A player that fails to play or plays an invalid action loses the game.
+// The game is a draw after max_turns turns.
+pub fn play_game(game: &mut CheckersGame, players: &[Rc<RefCell<dyn Player>>], max_turns: usize, seed: u64) -> GameRecord {
+    for (index, player) in players.iter().enumerate() {
+        player.borrow_mut().set_seed(player_seed(seed, index));
+    }
+
+    let levels = players.iter().map(|player| player.borrow().get_level()).collect();
+    let mut record = GameRecord { seed, levels, moves: vec![], result: GameResult::Draw };
+    let mut requests = 0;   // Actions of the current turn that are not moves
+    loop {
+        if let Some(result) = game.get_result() {
+            record.result = result;
+            return record;
+        }
+        let color = game.get_state().next_color;
+        let player = players.iter().find(|p| p.borrow().get_color() == color).expect("No player for the color to move");
+        if game.is_game_over(color) {
+            record.result = GameResult::Win(opposite_color(color));
+            return record;
+        }
+        if record.moves.len() >= max_turns {
+            record.result = GameResult::Draw;
+            return record;
+        }
+
+        let legal_moves = game.get_legal_movements();
+        let played = player.borrow_mut().play_turn(&game.get_state(), &legal_moves, None);
+        let action = match played {
+            Ok(action) => action,
+            Err(e) => {
+                println!("{} failed to play: {}", player.borrow().get_name(), e);
+                record.result = GameResult::Win(opposite_color(color));
+                return record;
+            }
+        };
+        let outcome = match game.apply_action(color, &action) {
+            Ok(ActionOutcome::DrawOffered) => {
+                let opponent_color = opposite_color(color);
+                let opponent = players.iter().find(|p| p.borrow().get_color() == opponent_color).expect("No player for the other color");
+                let answer = opponent.borrow_mut().answer_draw_offer(&game.get_state());
+                // An invalid answer declines the draw, the player offering it is not to blame
+                game.apply_action(opponent_color, &answer).or_else(|e| {
+                    println!("{} answered the draw offer with an invalid action {:?}: {} (game seed: {})", opponent.borrow().get_name(), answer, e, seed);
+                    game.apply_action(opponent_color, &Action::DeclineDraw)
+                })
+            }
+            outcome => outcome
+        };
+        match outcome {
+            Ok(ActionOutcome::Moved) | Ok(ActionOutcome::GameOver(_)) => {
+                if let Action::Move(action_move) = action {
+                    record.moves.push((color, action_move.tiles));
+                }
+                requests = 0;
+            }
+            Ok(ActionOutcome::Undone(n)) => {
+                record.moves.truncate(record.moves.len() - n);
+                requests += 1;
+            }
+            Ok(ActionOutcome::DrawOffered) | Ok(ActionOutcome::DrawDeclined) | Ok(ActionOutcome::Hint(_)) => {
+                requests += 1;
+            }
+            Err(e) => {
+                println!("{} played an invalid action {:?}: {} (game seed: {})", player.borrow().get_name(), action, e, seed);
+                record.result = GameResult::Win(opposite_color(color));
+                return record;
+            }
+        }
+        if requests > MAX_REQUESTS_PER_TURN {
+            println!("{} does not play (game seed: {})", player.borrow().get_name(), seed);
+            record.result = GameResult::Win(opposite_color(color));
+            return record;
+        }
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sm_checkers_base::movements::Movement;
+    use sm_checkers_players::player_mcts::MctsBudget;
+    use sm_checkers_players::player_trait::GameState;
+
+    // Offers a draw once, then plays its first legal move, and answers the draw offers with answer
+    struct DrawPlayer {
+        color: Color,
+        offered: bool,
+        answer: Action
+    }
+
+    impl Player for DrawPlayer {
+        fn get_name(&self) -> String {
+            format!("DRAW {:?}", self.color)
+        }
+
+        fn get_color(&self) -> Color {
+            self.color
+        }
+
+        fn play_turn(&mut self, _state: &GameState, legal_moves: &[Box<dyn Movement>], _time_left: Option<std::time::Duration>) -> Result<Action, String> {
+            if !self.offered {
+                self.offered = true;
+                return Ok(Action::OfferDraw);
+            }
+            Ok(Action::Move(ActionMove::new(self.color, &legal_moves[0].tiles())))
+        }
+
+        fn answer_draw_offer(&mut self, _state: &GameState) -> Action {
+            self.answer.clone()
+        }
+    }
+
+    fn play_seeded_game(seed: u64) -> GameRecord {
+        let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", Color::Black)));
+        random.borrow_mut().set_verbose(false);
+        let ai = Rc::new(RefCell::new(PlayerBotAI::new("AI", Color::Red)));
+        ai.borrow_mut().set_verbose(false);
+        let players: Vec<Rc<RefCell<dyn Player>>> = vec![random, ai];
+        play_game(&mut CheckersGame::new(), &players, 100, seed)
+    }
+
+    #[test]
+    fn test_replay_seed() {
+        let record = play_seeded_game(2024);
+        assert_eq!(record.seed, 2024);
+        assert!(!record.moves.is_empty());
+        // Same seed, same game
+        assert_eq!(play_seeded_game(record.seed), record);
+        assert_ne!(play_seeded_game(2025).moves, record.moves);
+
+        // MCTS, with an iteration budget
+        let play_mcts = |seed: u64| {
+            let mut mcts = PlayerMcts::new("MCTS", Color::Black);
+            mcts.set_budget(MctsBudget::Iterations(50));
+            mcts.set_verbose(false);
+            let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", Color::Red)));
+            random.borrow_mut().set_verbose(false);
+            let players: Vec<Rc<RefCell<dyn Player>>> = vec![Rc::new(RefCell::new(mcts)), random];
+            play_game(&mut CheckersGame::new(), &players, 20, seed)
+        };
+        assert_eq!(play_mcts(7), play_mcts(7));
+
+        // The levels of the players
+        let mut search = PlayerBotSearch::new("SEARCH", Color::Black);
+        search.set_level(BotLevel::Beginner);
+        search.set_verbose(false);
+        let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", Color::Red)));
+        random.borrow_mut().set_verbose(false);
+        let players: Vec<Rc<RefCell<dyn Player>>> = vec![Rc::new(RefCell::new(search)), random];
+        let record = play_game(&mut CheckersGame::new(), &players, 10, 1);
+        assert_eq!(record.levels, vec![Some(BotLevel::Beginner), None]);
+    }
+
+    #[test]
+    fn test_color_to_move() {
+        let new_random = |color: Color| -> Rc<RefCell<dyn Player>> {
+            let mut random = PlayerBotRandom::new("RANDOM", color);
+            random.set_verbose(false);
+            Rc::new(RefCell::new(random))
+        };
+        // Red plays first when Black has played already, whatever the order of the players
+        let mut game = CheckersGame::new();
+        game.apply_action(Color::Black, &Action::Move(ActionMove::new(Color::Black, &vec![8, 12]))).unwrap();
+        let record = play_game(&mut game, &[new_random(Color::Black), new_random(Color::Red)], 5, 3);
+        assert_eq!(record.moves.len(), 5);
+        assert_eq!(record.moves.iter().map(|(color, _)| *color).collect::<Vec<Color>>(),
+            vec![Color::Red, Color::Black, Color::Red, Color::Black, Color::Red]);
+
+        let record = play_game(&mut CheckersGame::new(), &[new_random(Color::Red), new_random(Color::Black)], 2, 3);
+        assert_eq!(record.moves.iter().map(|(color, _)| *color).collect::<Vec<Color>>(), vec![Color::Black, Color::Red]);
+    }
+
+    #[test]
+    fn test_invalid_draw_answer() {
+        // Undo is not an answer to a draw offer: the draw is declined and the game goes on
+        let new_player = |color: Color, answer: Action| -> Rc<RefCell<dyn Player>> {
+            Rc::new(RefCell::new(DrawPlayer { color, offered: false, answer }))
+        };
+        let players = vec![new_player(Color::Black, Action::DeclineDraw), new_player(Color::Red, Action::Undo)];
+        let record = play_game(&mut CheckersGame::new(), &players, 6, 1);
+        assert_eq!(record.moves.len(), 6);
+        assert_eq!(record.result, GameResult::Draw);
+
+        let players = vec![new_player(Color::Black, Action::DeclineDraw), new_player(Color::Red, Action::AcceptDraw)];
+        let record = play_game(&mut CheckersGame::new(), &players, 6, 1);
+        assert!(record.moves.is_empty());
+        assert_eq!(record.result, GameResult::Draw);
+
+        // Red offers first, after a move of Black: Black answers
+        let mut game = CheckersGame::new();
+        game.apply_action(Color::Black, &Action::Move(ActionMove::new(Color::Black, &vec![8, 12]))).unwrap();
+        let players = vec![new_player(Color::Black, Action::AcceptDraw), new_player(Color::Red, Action::Undo)];
+        let record = play_game(&mut game, &players, 6, 1);
+        assert!(record.moves.is_empty());
+        assert_eq!(record.result, GameResult::Draw);
+    }
+}
@@ -2,15 +2,30 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 use sm_checkers_base::Color;
+use sm_checkers_base::GameResult;
+use sm_checkers_base::player_colors::opposite_color;
+use sm_checkers_base::notation::movement_to_string;
+use sm_checkers_base::pdn::load_pdn_file;
+use sm_checkers_base::endgame_db;
+use sm_checkers_base::endgame_probe::{EndgameProbe, DEFAULT_CACHE_MB};
 
 use sm_checkers_players::*;
-use crate::cyclic_iterator::CyclicIterator;
+use sm_checkers_players::player_bot_ai::LearningRule;
+use sm_checkers_players::neural_evaluator::MlpEvaluator;
+use sm_checkers_players::engine_protocol::serve_engine;
+use sm_checkers_players::opening_book::{BookStrength, OpeningBook};
 
 use crate::checkers_ui::CheckersUi;
 use crate::checkers_ui_text::CheckersUiText;
 
 use crate::checkers_game::CheckersGame;
+use crate::checkers_game::ActionOutcome;
 use crate::checkers_game::Subject;
+use crate::training::*;
+use crate::evolution::*;
+use crate::data_export::*;
+use crate::game_runner::player_seed;
+use crate::network_client::run_client;
 
 
 mod checkers_game;
@@ -18,12 +33,542 @@ mod checkers_game;
 mod checkers_ui;
 mod checkers_ui_text;
 
-mod cyclic_iterator;
+mod game_runner;
+mod network_client;
+mod training;
+mod evolution;
+mod data_export;
 
 
-fn main() {
+// Command line:
+//   sm-checkers [--human] [--threads N] [--ai-weights FILE] [--search-mlp FILE] [--engine "PROGRAM ARGS"]
+//               [--listen ADDRESS] [--script FILE [--script-game N]] [--book FILE [--book-strength N]]
+//               [--endgame-db DIR] [--level LEVEL] [--ponder] [--seed S]
+//                                      Plays a game, N: number of threads used by the search bot
+//                                      --human: play the black pieces in the console instead of the search bot
+//                                      --engine: external engine playing the red pieces instead of the AI bot
+//                                      --listen: the red pieces are played by a remote player, connected to
+//                                      ADDRESS (ex: 0.0.0.0:7878) with "sm-checkers connect"
+//                                      --script: the moves of the N-th game (1 by default) of the PDN FILE
+//                                      are played first, then the players take over
+//                                      --book: the bots play the moves of the opening book FILE first,
+//                                      N: 1 all moves, 2 good moves (default), 3 best moves
+//                                      --endgame-db: the search bot probes the endgame databases of DIR
+//                                      --level: strength of the search bot, beginner, novice, intermediate,
+//                                      advanced or expert (or 1 to 5), unlimited by default
+//                                      --ponder: the search bot thinks while its opponent thinks
+//                                      S: seed of the game, printed at the start, to replay it
+//                                      FILE: weights of the AI bot, saved by the training
+//                                      --search-mlp: network evaluating the leaves of the search bot
+//   sm-checkers train [--model linear|mlp] [--hidden N,N...] [--games N] [--opponent self|random] [--seed S]
+//                     [--report-every N] [--eval-games N] [--max-turns N]
+//                     [--load FILE] [--save FILE] [--learner lms|td] [--lambda L] [--eta E]
+//                                      Trains PlayerBotAI (linear) or an MlpEvaluator without any display
+//   sm-checkers compare FILE_A FILE_B [--games N] [--seed S]
+//                                      Plays the weights of FILE_A against the weights of FILE_B
+//   sm-checkers evolve [--population N] [--generations N] [--games N] [--survivors N] [--mutation M]
+//                      [--seed S] [--eval-games N] [--max-turns N] [--load FILE] [--save FILE]
+//                                      Evolves the weights of the AI bot with a genetic algorithm, N games
+//                                      for each pair of the tournament of each generation
+//   sm-checkers engine [--threads N] [--depth N] [--time MS] [--search-mlp FILE] [--endgame-db DIR] [--level LEVEL]
+//                                      Reference engine: the search bot speaking the protocol of
+//                                      engine_protocol.rs on stdin/stdout
+//   sm-checkers connect ADDRESS [--name NAME]
+//                                      Plays in the console the game of a host started with --listen
+//   sm-checkers book PDN_FILE... [--plies N] [--save FILE]
+//                                      Builds an opening book with the first N plies of the games
+//   sm-checkers endgame [--pieces N] [--dir DIR]
+//                                      Generates the endgame databases of up to N pieces (4 by default)
+//                                      in DIR (sm_checkers_db by default), resumed if interrupted
+//   sm-checkers tune PDN_FILE... [--load FILE] [--save FILE] [--passes N] [--skip-plies N]
+//                                      Tunes the weights of the AI bot on the results of the games (Texel method),
+//                                      starting from FILE or the initial weights, skipping the first N plies
+//   sm-checkers export [PDN_FILE...] [--games N] [--level LEVEL] [--depth N] [--max-turns N] [--seed S]
+//                      [--format csv|binary] [--output FILE]
+//                                      Writes the positions of the games, with their search score at depth N
+//                                      (6 by default) and the result of the game, see data_export.rs.
+//                                      Without PDN files, N games are played by search bots of LEVEL
+enum Command {
+    Play(PlayOptions),
+    Train(TrainingOptions),
+    Compare(CompareOptions),
+    Evolve(EvolutionOptions),
+    Engine(EngineOptions),
+    Connect(ConnectOptions),
+    Book(BookOptions),
+    Endgame(EndgameOptions),
+    Tune(TuneOptions),
+    Export(ExportOptions)
+}
+
+struct PlayOptions {
+    human: bool,
+    threads: usize,
+    ai_weights: Option<String>,
+    search_mlp: Option<String>,
+    endgame_db: Option<String>,
+    level: Option<BotLevel>,
+    ponder: bool,
+    engine: Option<String>,
+    listen: Option<String>,
+    script: Option<String>,
+    script_game: usize,
+    book: Option<String>,
+    book_strength: u32,
+    seed: u64
+}
+
+struct BookOptions {
+    pdn_files: Vec<String>,
+    plies: usize,
+    save: String
+}
+
+struct TuneOptions {
+    pdn_files: Vec<String>,
+    load: Option<String>,
+    save: String,
+    passes: usize,
+    skip_plies: usize
+}
+
+struct EndgameOptions {
+    pieces: usize,
+    dir: String
+}
+
+struct ConnectOptions {
+    address: String,
+    name: String
+}
+
+struct EngineOptions {
+    threads: usize,
+    depth: u32,
+    time_ms: u64,
+    search_mlp: Option<String>,
+    endgame_db: Option<String>,
+    level: Option<BotLevel>
+}
+
+struct CompareOptions {
+    file_a: String,
+    file_b: String,
+    games: usize,
+    seed: u64
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    value.and_then(|v| v.parse().ok()).ok_or(format!("{} expects a number", option))
+}
+
+fn parse_string(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or(format!("{} expects a file name", option))
+}
+
+fn parse_level(option: &str, value: Option<String>) -> Result<BotLevel, String> {
+    value.as_deref().and_then(BotLevel::from_name).ok_or(format!("{} expects beginner, novice, intermediate, advanced or expert", option))
+}
+
+fn unknown_option(arg: &str) -> String {
+    format!("Unknown option: {}", arg)
+}
+
+// Without a seed, use the time: the seed is printed, so that the run can be reproduced
+fn time_seed() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+}
+
+// Each subcommand has its own options, the game is played without a subcommand
+fn parse_args() -> Result<Command, String> {
+    let mut args = std::env::args().skip(1).peekable();
+    let subcommand = args.peek().cloned();
+    Ok(match subcommand.as_deref() {
+        Some("train") => Command::Train(parse_train_args(args.skip(1))?),
+        Some("evolve") => Command::Evolve(parse_evolve_args(args.skip(1))?),
+        Some("compare") => Command::Compare(parse_compare_args(args.skip(1))?),
+        Some("engine") => Command::Engine(parse_engine_args(args.skip(1))?),
+        Some("connect") => Command::Connect(parse_connect_args(args.skip(1))?),
+        Some("book") => Command::Book(parse_book_args(args.skip(1))?),
+        Some("endgame") => Command::Endgame(parse_endgame_args(args.skip(1))?),
+        Some("tune") => Command::Tune(parse_tune_args(args.skip(1))?),
+        Some("export") => Command::Export(parse_export_args(args.skip(1))?),
+        _ => Command::Play(parse_play_args(args)?)
+    })
+}
+
+fn parse_play_args(mut args: impl Iterator<Item = String>) -> Result<PlayOptions, String> {
+    let mut options = PlayOptions {
+        human: false,
+        threads: 1,
+        ai_weights: None,
+        search_mlp: None,
+        endgame_db: None,
+        level: None,
+        ponder: false,
+        engine: None,
+        listen: None,
+        script: None,
+        script_game: 1,
+        book: None,
+        book_strength: 2,
+        seed: time_seed()
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--human" => options.human = true,
+            "--ponder" => options.ponder = true,
+            "--threads" => options.threads = parse_number(&arg, args.next())?,
+            "--ai-weights" => options.ai_weights = Some(parse_string(&arg, args.next())?),
+            "--search-mlp" => options.search_mlp = Some(parse_string(&arg, args.next())?),
+            "--endgame-db" => options.endgame_db = Some(parse_string(&arg, args.next())?),
+            "--level" => options.level = Some(parse_level(&arg, args.next())?),
+            "--engine" => options.engine = Some(parse_string(&arg, args.next())?),
+            "--listen" => options.listen = Some(parse_string(&arg, args.next())?),
+            "--script" => options.script = Some(parse_string(&arg, args.next())?),
+            "--script-game" => options.script_game = parse_number(&arg, args.next())?,
+            "--book" => options.book = Some(parse_string(&arg, args.next())?),
+            "--book-strength" => options.book_strength = parse_number(&arg, args.next())?,
+            "--seed" => options.seed = parse_number(&arg, args.next())?,
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_train_args(mut args: impl Iterator<Item = String>) -> Result<TrainingOptions, String> {
+    let mut options = TrainingOptions::new();
+    options.seed = time_seed();
+    let mut learner = None;     // Some(true) for TD(lambda)
+    let mut lambda = 0.7;
+    let mut hidden_layers = vec![32];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&arg, args.next())?,
+            "--seed" => options.seed = parse_number(&arg, args.next())?,
+            "--report-every" => options.report_every = parse_number(&arg, args.next())?,
+            "--eval-games" => options.eval_games = parse_number(&arg, args.next())?,
+            "--max-turns" => options.max_turns = parse_number(&arg, args.next())?,
+            "--load" => options.load = Some(parse_string(&arg, args.next())?),
+            "--save" => options.save = Some(parse_string(&arg, args.next())?),
+            "--eta" => options.eta = Some(parse_number(&arg, args.next())?),
+            "--lambda" => {
+                lambda = parse_number(&arg, args.next())?;
+                if !(0.0..=1.0).contains(&lambda) {
+                    return Err("--lambda expects a number between 0 and 1".into());
+                }
+            }
+            "--model" => {
+                options.model = match args.next().as_deref() {
+                    Some("linear") => TrainingModel::Linear,
+                    Some("mlp") => TrainingModel::Mlp(hidden_layers.clone()),
+                    _ => return Err("--model expects linear or mlp".into())
+                }
+            }
+            "--hidden" => {
+                let value = parse_string(&arg, args.next())?;
+                hidden_layers = value.split(',').map(|n| parse_number(&arg, Some(n.to_string()))).collect::<Result<Vec<usize>, String>>()?;
+                if let TrainingModel::Mlp(_) = options.model {
+                    options.model = TrainingModel::Mlp(hidden_layers.clone());
+                }
+            }
+            "--learner" => {
+                learner = match args.next().as_deref() {
+                    Some("lms") => Some(false),
+                    Some("td") => Some(true),
+                    _ => return Err("--learner expects lms or td".into())
+                }
+            }
+            "--opponent" => {
+                options.opponent = match args.next().as_deref() {
+                    Some("self") => TrainingOpponent::SelfPlay,
+                    Some("random") => TrainingOpponent::Random,
+                    _ => return Err("--opponent expects self or random".into())
+                }
+            }
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    options.learning_rule = learner.map(|td| if td { LearningRule::TdLambda(lambda) } else { LearningRule::Lms });
+    Ok(options)
+}
+
+fn parse_evolve_args(mut args: impl Iterator<Item = String>) -> Result<EvolutionOptions, String> {
+    let mut options = EvolutionOptions::new();
+    options.seed = time_seed();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--population" => options.population = parse_number(&arg, args.next())?,
+            "--generations" => options.generations = parse_number(&arg, args.next())?,
+            "--games" => options.games = parse_number(&arg, args.next())?,
+            "--survivors" => options.survivors = parse_number(&arg, args.next())?,
+            "--mutation" => options.mutation = parse_number(&arg, args.next())?,
+            "--seed" => options.seed = parse_number(&arg, args.next())?,
+            "--eval-games" => options.eval_games = parse_number(&arg, args.next())?,
+            "--max-turns" => options.max_turns = parse_number(&arg, args.next())?,
+            "--load" => options.load = Some(parse_string(&arg, args.next())?),
+            "--save" => options.save = Some(parse_string(&arg, args.next())?),
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_compare_args(mut args: impl Iterator<Item = String>) -> Result<CompareOptions, String> {
+    let mut options = CompareOptions {
+        file_a: parse_string("compare", args.next())?,
+        file_b: parse_string("compare", args.next())?,
+        games: 100,
+        seed: 0
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&arg, args.next())?,
+            "--seed" => options.seed = parse_number(&arg, args.next())?,
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_engine_args(mut args: impl Iterator<Item = String>) -> Result<EngineOptions, String> {
+    let mut options = EngineOptions { threads: 1, depth: 10, time_ms: 1000, search_mlp: None, endgame_db: None, level: None };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => options.threads = parse_number(&arg, args.next())?,
+            "--depth" => options.depth = parse_number(&arg, args.next())?,
+            "--time" => options.time_ms = parse_number(&arg, args.next())?,
+            "--search-mlp" => options.search_mlp = Some(parse_string(&arg, args.next())?),
+            "--endgame-db" => options.endgame_db = Some(parse_string(&arg, args.next())?),
+            "--level" => options.level = Some(parse_level(&arg, args.next())?),
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_connect_args(mut args: impl Iterator<Item = String>) -> Result<ConnectOptions, String> {
+    let mut options = ConnectOptions {
+        address: parse_string("connect", args.next())?,
+        name: "Guest".to_string()
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => options.name = parse_string(&arg, args.next())?,
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_book_args(mut args: impl Iterator<Item = String>) -> Result<BookOptions, String> {
+    let mut options = BookOptions { pdn_files: vec![], plies: 16, save: "book.json".to_string() };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--plies" => options.plies = parse_number(&arg, args.next())?,
+            "--save" => options.save = parse_string(&arg, args.next())?,
+            _ if !arg.starts_with("--") => options.pdn_files.push(arg),
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_endgame_args(mut args: impl Iterator<Item = String>) -> Result<EndgameOptions, String> {
+    let mut options = EndgameOptions { pieces: 4, dir: "sm_checkers_db".to_string() };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pieces" => options.pieces = parse_number(&arg, args.next())?,
+            "--dir" => options.dir = parse_string(&arg, args.next())?,
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_tune_args(mut args: impl Iterator<Item = String>) -> Result<TuneOptions, String> {
+    let mut options = TuneOptions { pdn_files: vec![], load: None, save: "tuned.json".to_string(), passes: 100, skip_plies: 8 };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--load" => options.load = Some(parse_string(&arg, args.next())?),
+            "--save" => options.save = parse_string(&arg, args.next())?,
+            "--passes" => options.passes = parse_number(&arg, args.next())?,
+            "--skip-plies" => options.skip_plies = parse_number(&arg, args.next())?,
+            _ if !arg.starts_with("--") => options.pdn_files.push(arg),
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_export_args(mut args: impl Iterator<Item = String>) -> Result<ExportOptions, String> {
+    let mut options = ExportOptions::new();
+    options.seed = time_seed();
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => options.games = parse_number(&arg, args.next())?,
+            "--level" => options.level = parse_level(&arg, args.next())?,
+            "--depth" => options.depth = parse_number(&arg, args.next())?,
+            "--max-turns" => options.max_turns = parse_number(&arg, args.next())?,
+            "--seed" => options.seed = parse_number(&arg, args.next())?,
+            "--output" => output = Some(parse_string(&arg, args.next())?),
+            "--format" => {
+                options.format = match args.next().as_deref() {
+                    Some("csv") => ExportFormat::Csv,
+                    Some("binary") => ExportFormat::Binary,
+                    _ => return Err("--format expects csv or binary".into())
+                }
+            }
+            _ if !arg.starts_with("--") => options.pdn_files.push(arg),
+            _ => return Err(unknown_option(&arg))
+        }
+    }
+    options.output = output.unwrap_or_else(|| if options.format == ExportFormat::Csv { "positions.csv" } else { "positions.bin" }.to_string());
+    Ok(options)
+}
+
+fn build_book(options: &BookOptions) -> Result<(), String> {
+    if options.pdn_files.is_empty() {
+        return Err("book expects PDN files".into());
+    }
+    let mut book = OpeningBook::new(options.plies);
+    for path in &options.pdn_files {
+        let games = load_pdn_file(path)?;
+        let (added, errors) = book.add_games(&games);
+        for e in errors.iter() {
+            println!("{}: {}", path, e);
+        }
+        println!("{}: {} games added, {} skipped", path, added, errors.len());
+    }
+    book.save(&options.save)?;
+    println!("{} positions saved to {}", book.positions.len(), options.save);
+    Ok(())
+}
+
+fn tune_weights(options: &TuneOptions) -> Result<(), String> {
+    if options.pdn_files.is_empty() {
+        return Err("tune expects PDN files".into());
+    }
+    let mut tuner = TexelTuner::new(options.skip_plies);
+    let mut games = 0;
+    for path in &options.pdn_files {
+        let (added, errors) = tuner.add_games(&load_pdn_file(path)?);
+        for e in errors.iter() {
+            println!("{}: {}", path, e);
+        }
+        println!("{}: {} games added, {} skipped", path, added, errors.len());
+        games += added;
+    }
+    if tuner.get_positions().is_empty() {
+        return Err("No quiet position in the games".into());
+    }
+
+    // The learning metadata of the loaded file is kept
+    let mut player = match &options.load {
+        Some(path) => PlayerBotAI::from_weights_file("AI", Color::Black, path)?,
+        None => PlayerBotAI::new("AI", Color::Black)
+    };
+    let start = player.get_evaluator().get_weights().to_vec();
+    let k = tuner.fit_scale(&start);
+    println!("{} quiet positions of {} games, K: {:.4}, mean squared error: {:.6}", tuner.get_positions().len(), games, k, tuner.mean_error(&start, k));
+    let weights = tuner.tune(&start, k, options.passes, &mut |pass, step, error| {
+        println!("Pass {}: step {}, mean squared error: {:.6}", pass, step, error);
+    });
+    player.set_weights(&weights);
+    player.save_weights_file(&options.save)?;
+    println!("Weights saved to {}", options.save);
+    Ok(())
+}
+
+fn generate_endgame(options: &EndgameOptions) -> Result<(), String> {
+    let start = std::time::Instant::now();
+    let db = endgame_db::generate(options.pieces, std::path::Path::new(&options.dir), &mut |table, loaded| {
+        let (wins, losses, draws) = endgame_db::count_values(table);
+        println!("{:?}: {} positions, {} wins, {} losses, {} draws{} ({:.1}s)",
+            table.get_index().get_material(), 2 * table.get_index().size(), wins, losses, draws,
+            if loaded { ", already generated" } else { "" }, start.elapsed().as_secs_f64());
+    })?;
+    println!("{} databases, {} bytes, saved in {}", db.get_materials().len(), db.bytes(), options.dir);
+    Ok(())
+}
+
+fn compare_files(options: &CompareOptions) -> Result<(), String> {
+    let load = |path: &str| -> Result<Vec<f64>, String> {
+        Ok(PlayerBotAI::from_weights_file("AI", Color::Black, path)?.get_evaluator().get_weights().to_vec())
+    };
+    let score = compare_weights(&load(&options.file_a)?, &load(&options.file_b)?, options.games, options.seed, TrainingOptions::new().max_turns);
+    println!("{} against {}: {} wins, {} draws, {} losses ({})",
+        options.file_a, options.file_b, score.wins, score.draws, score.losses, score.percent_string());
+    Ok(())
+}
+
+fn connect(options: &ConnectOptions) -> Result<(), String> {
+    let stream = std::net::TcpStream::connect(&options.address).map_err(|e| format!("Failed to connect to {}: {}", options.address, e))?;
+    run_client(stream, &options.name, Box::new(std::io::BufReader::new(std::io::stdin())))
+}
 
+// The search bot as an external engine: nothing but the protocol is written on stdout
+fn run_engine(options: &EngineOptions) -> Result<(), String> {
+    let evaluator = match &options.search_mlp {
+        Some(path) => Some(std::sync::Arc::new(MlpEvaluator::load(path)?) as std::sync::Arc<dyn Evaluator>),
+        None => None
+    };
+    let endgame_db = match &options.endgame_db {
+        Some(dir) => Some(std::sync::Arc::new(EndgameProbe::open(std::path::Path::new(dir), DEFAULT_CACHE_MB)?)),
+        None => None
+    };
+    let mut new_player = |color: Color| -> Box<dyn Player> {
+        let mut player = PlayerBotSearch::new("SM-CHECKERS ENGINE", color);
+        player.set_verbose(false);
+        player.set_threads(options.threads);
+        player.set_max_depth(options.depth);
+        player.set_max_time(std::time::Duration::from_millis(options.time_ms));
+        if let Some(level) = options.level {
+            player.set_level(level);
+        }
+        if let Some(evaluator) = &evaluator {
+            player.set_evaluator(evaluator.clone());
+        }
+        if let Some(endgame_db) = &endgame_db {
+            player.set_endgame_db(endgame_db.clone());
+        }
+        Box::new(player)
+    };
+    let stdin = std::io::stdin();
+    serve_engine("sm-checkers search", &mut stdin.lock(), &mut std::io::stdout(), &mut new_player)
+}
 
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Play(options) => play(&options),
+        Command::Train(options) => {
+            let trained = match options.model {
+                TrainingModel::Linear => run_training(&options).map(|_| ()),
+                TrainingModel::Mlp(_) => run_mlp_training(&options).map(|_| ())
+            };
+            trained.map_err(|e| format!("Training failed: {}", e))
+        }
+        Command::Evolve(options) => run_evolution(&options).map(|_| ()).map_err(|e| format!("Evolution failed: {}", e)),
+        Command::Compare(options) => compare_files(&options),
+        Command::Engine(options) => run_engine(&options),
+        Command::Connect(options) => connect(&options),
+        Command::Book(options) => build_book(&options),
+        Command::Endgame(options) => generate_endgame(&options),
+        Command::Tune(options) => tune_weights(&options),
+        Command::Export(options) => run_export(&options).map(|_| ())
+    }
+}
+
+// The errors go to stderr, stdout being the protocol of the engine
+fn main() {
+    if let Err(e) = parse_args().and_then(run) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn play(options: &PlayOptions) -> Result<(), String> {
     // Create UI
     let gui = Rc::new(RefCell::new(CheckersUiText::new()));
     gui.borrow_mut().splash_screen();
@@ -32,59 +577,186 @@ fn main() {
     let mut game = CheckersGame::new();
 
     // Create Players
-    //let human = Rc::new(RefCell::new(PlayerHumanConsole::new("Player 1", Color::Black)));
     //let bot1 = Rc::new(RefCell::new(PlayerBotRandom::new("ZE BOT A", Color::Black)));
     //let bot2 = Rc::new(RefCell::new(PlayerBotRandom::new("ZE BOT II", Color::Red)));
-    let bot1 = Rc::new(RefCell::new(PlayerBotAI::new("AI BOT 1", Color::Black)));
-    let bot2 = Rc::new(RefCell::new(PlayerBotAI::new("AI BOT 2", Color::Red)));
+    let bot1 = Rc::new(RefCell::new(PlayerBotSearch::new("SEARCH BOT 1", Color::Black)));
+    bot1.borrow_mut().set_threads(options.threads);
+    if let Some(level) = options.level {
+        bot1.borrow_mut().set_level(level);
+    }
+    bot1.borrow_mut().set_pondering(options.ponder);
+    if let Some(path) = &options.search_mlp {
+        bot1.borrow_mut().set_evaluator(std::sync::Arc::new(MlpEvaluator::load(path)?));
+    }
+    if let Some(dir) = &options.endgame_db {
+        bot1.borrow_mut().set_endgame_db(std::sync::Arc::new(EndgameProbe::open(std::path::Path::new(dir), DEFAULT_CACHE_MB)?));
+    }
+    let bot2 = match &options.ai_weights {
+        Some(path) => Rc::new(RefCell::new(PlayerBotAI::from_weights_file("AI BOT 2", Color::Red, path)?)),
+        None => Rc::new(RefCell::new(PlayerBotAI::new("AI BOT 2", Color::Red)))
+    };
 
+    // The players receive the game state when they play, only the UI observes the game
     game.register_observer(gui.clone());
-    //game.register_observer(human.clone()); 
-    game.register_observer(bot1.clone()); 
-    game.register_observer(bot2.clone()); 
 
 
-    //let players: Vec<Rc<RefCell<dyn Player>>> = vec![human.clone(), bot.clone()];
-    let players: Vec<Rc<RefCell<dyn Player>>> = vec![bot1.clone(), bot2.clone()];
+    let book = match &options.book {
+        Some(path) => Some(std::sync::Arc::new(OpeningBook::load(path)?)),
+        None => None
+    };
+    let book_strength = BookStrength::from_level(options.book_strength).ok_or("--book-strength expects 0, 1, 2 or 3")?;
+    // Only the bots of this program use the book
+    let with_book = |player: Rc<RefCell<dyn Player>>| -> Rc<RefCell<dyn Player>> {
+        match &book {
+            Some(book) => Rc::new(RefCell::new(PlayerBook::new(book.clone(), book_strength, player))),
+            None => player
+        }
+    };
+
+    let black: Rc<RefCell<dyn Player>> = if options.human {
+        Rc::new(RefCell::new(PlayerHumanConsole::new("Player 1", Color::Black)))
+    } else {
+        with_book(bot1.clone())
+    };
+    let red: Rc<RefCell<dyn Player>> = match (&options.engine, &options.listen) {
+        (_, Some(address)) => {
+            println!("Waiting for a player on {}...", address);
+            let remote = PlayerNetwork::listen("REMOTE PLAYER", Color::Red, address.as_str())?;
+            println!("Remote player: {}", remote.get_remote_name());
+            Rc::new(RefCell::new(remote))
+        }
+        (Some(command), None) => {
+            let mut words = command.split_whitespace().map(|w| w.to_string());
+            let program = words.next().unwrap_or_default();
+            let engine = PlayerExternal::new("ENGINE 2", Color::Red, &program, &words.collect::<Vec<String>>())?;
+            println!("External engine: {}", engine.get_engine_name());
+            Rc::new(RefCell::new(engine))
+        }
+        (None, None) => with_book(bot2.clone())
+    };
+    let mut players: Vec<Rc<RefCell<dyn Player>>> = vec![black, red];
+    if let Some(path) = &options.script {
+        let games = load_pdn_file(path)?;
+        if options.script_game < 1 || options.script_game > games.len() {
+            return Err(format!("{} has {} games, no game {}", path, games.len(), options.script_game));
+        }
+        let game = games[options.script_game - 1].clone();
+        if game.tag("FEN").is_some() {
+            return Err("Games starting from a FEN position cannot be replayed".into());
+        }
+        println!("Replaying {} moves of {}", game.moves.len(), path);
+        players = players.into_iter().map(|player| {
+            let mut scripted = PlayerScripted::from_pdn_game(&player.borrow().get_name(), player.borrow().get_color(), &game);
+            scripted.set_fallback(player);
+            Rc::new(RefCell::new(scripted)) as Rc<RefCell<dyn Player>>
+        }).collect();
+    }
+    println!("Game seed: {}", options.seed);
+    for (index, player) in players.iter().enumerate() {
+        player.borrow_mut().set_seed(player_seed(options.seed, index));
+        if let Some(level) = player.borrow().get_level() {
+            println!("{}: level {}", player.borrow().get_name(), level.name());
+        }
+    }
 
-    let mut players_cyclic_iter = CyclicIterator::new(&players);
     let mut nb_turns = 0;
-    for player in players_cyclic_iter.by_ref() {
-        if game.is_game_over((*player).borrow().get_color()) {
-            println!("{} has lost!", (*player).borrow().get_name());
-            println!("{} has won!", (players_cyclic_iter.next()).unwrap().borrow().get_name());
-            println!("Number of turns: {}", nb_turns);
+    let result;
+    'game: loop {
+        let color = game.get_state().next_color;
+        let player = players.iter().find(|p| p.borrow().get_color() == color).unwrap();
+        if game.is_game_over(color) {
+            result = Some(GameResult::Win(opposite_color(color)));
             break;
         }
 
         let mut action_valid = false;
         while !action_valid {
-            println!("{}'s turn - You have the {:?} pieces", (*player).borrow().get_name(), (*player).borrow().get_color());
-
-            let ac = player.borrow().play_turn();
-            if ac.as_any().downcast_ref::<player_actions::ActionMove>().is_some() {
-                if let Some(ac_move) = ac.as_any().downcast_ref::<player_actions::ActionMove>() {
-                    println!("Move: {:?}", ac_move);
-                    match game.move_piece(ac_move) {
-                        Ok(_) => {
-                            action_valid = true;
-                            nb_turns += 1;
-                        }
-                        Err(e) => {
-                            println!("Your move was invalid: {}", e);
-                        }
-                    }
+            // The other players may think on this player's time
+            for other in players.iter().filter(|p| !Rc::ptr_eq(p, player)) {
+                other.borrow_mut().start_pondering(&game.get_state());
+            }
+            println!("{}'s turn - You have the {:?} pieces", (*player).borrow().get_name(), color);
+
+            let legal_moves = game.get_legal_movements();
+            let played = player.borrow_mut().play_turn(&game.get_state(), &legal_moves, None);
+            let ac = match played {
+                Ok(ac) => ac,
+                Err(e) => {
+                    println!("{} is unable to play: {}", (*player).borrow().get_name(), e);
+                    result = Some(GameResult::Win(opposite_color(color)));
+                    break 'game;
                 }
+            };
+            match &ac {
+                Action::Move(ac_move) => println!("Move: {}", movement_to_string(ac_move.to_movement().as_ref())),
+                _ => println!("Action: {:?}", ac)
             }
-            else if ac.as_any().downcast_ref::<player_actions::ActionQuit>().is_some() {
-                if let Some(ac_quit) = ac.as_any().downcast_ref::<player_actions::ActionQuit>() {
-                    println!("Quit: {:?}", ac_quit);
-                    println!("Bye!");
-                    std::process::exit(0);
+            match game.apply_action(color, &ac) {
+                Ok(ActionOutcome::Moved) => {
+                    action_valid = true;
+                    nb_turns += 1;
                 }
-            }              
+                Ok(ActionOutcome::GameOver(game_result)) => {
+                    if let Action::Move(_) = ac {
+                        nb_turns += 1;
+                    }
+                    if ac == Action::Quit {
+                        println!("Bye!");
+                    }
+                    result = Some(game_result);
+                    break 'game;
+                }
+                Ok(ActionOutcome::DrawOffered) => {
+                    let opponent = players.iter().find(|p| p.borrow().get_color() != color).unwrap();
+                    let opponent_color = opponent.borrow().get_color();
+                    let answer = opponent.borrow_mut().answer_draw_offer(&game.get_state());
+                    let outcome = game.apply_action(opponent_color, &answer).or_else(|e| {
+                        println!("Invalid answer to the draw offer: {}", e);
+                        game.apply_action(opponent_color, &Action::DeclineDraw)
+                    });
+                    if let Ok(ActionOutcome::GameOver(game_result)) = outcome {
+                        result = Some(game_result);
+                        break 'game;
+                    }
+                    println!("{} declines the draw", opponent.borrow().get_name());
+                }
+                Ok(ActionOutcome::DrawDeclined) => {}
+                Ok(ActionOutcome::Undone(n)) => {
+                    println!("{} moves taken back", n);
+                    nb_turns -= n;
+                }
+                Ok(ActionOutcome::Hint(analysis)) => {
+                    for line in analysis.iter() {
+                        println!("Hint: {}", line.to_notation(color));
+                    }
+                }
+                Err(e) => {
+                    println!("Your action was invalid: {}", e);
+                }
+            }
+        }
+    }
+
+    for player in players.iter() {
+        player.borrow_mut().stop_pondering();
+    }
+
+    let name_of = |color: Color| players.iter().find(|p| p.borrow().get_color() == color).unwrap().borrow().get_name();
+    match result {
+        Some(GameResult::Win(winner)) => {
+            println!("{} has lost!", name_of(opposite_color(winner)));
+            println!("{} has won!", name_of(winner));
         }
+        Some(GameResult::Draw) => println!("Draw!"),
+        None => {}
+    }
+    println!("Number of turns: {}", nb_turns);
+
+    // Let the learning bot learn from this game
+    if let (Some(result), None, None) = (result, &options.engine, &options.listen) {
+        bot2.borrow_mut().learn_from_game(&result);
     }
     println!("GAME OVER");
+    Ok(())
 }
 
@@ -0,0 +1,169 @@
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use sm_checkers_base::Color;
+use sm_checkers_base::movements::Movement;
+use sm_checkers_players::*;
+use sm_checkers_players::player_trait::GameState;
+use sm_checkers_players::engine_protocol::serve_engine;
+
+use crate::checkers_game::HintEngine;
+use crate::checkers_ui::CheckersUi;
+use crate::checkers_ui_text::CheckersUiText;
+
+// Client side of a network game: the host runs the game ("sm-checkers --listen ADDRESS"),
+// and sends the position each time the person on this side has to play.
+// The moves typed here are checked against the legal moves before they are sent,
+// and checked again by the host.
+
+// The console player, with the board drawn before each move
+struct PlayerRemoteConsole {
+    human: PlayerHumanConsole,
+    ui: CheckersUiText,
+    hint_engine: HintEngine
+}
+
+impl Player for PlayerRemoteConsole {
+    fn get_color(&self) -> Color {
+        self.human.get_color()
+    }
+    fn get_name(&self) -> String {
+        self.human.get_name()
+    }
+
+    fn play_turn(&mut self, state: &GameState, legal_moves: &[Box<dyn Movement>], time_left: Option<Duration>) -> Result<Action, String> {
+        self.ui.draw_board(&state.board);
+        println!("Turn {} - You have the {:?} pieces", state.turn + 1, self.get_color());
+        loop {
+            match self.human.play_turn(state, legal_moves, time_left)? {
+                // The host does not answer hints and undos: they are handled here
+                Action::Hint => {
+                    for line in self.hint_engine.analyze(&state.board, self.get_color()) {
+                        println!("Hint: {}", line.to_notation(self.get_color()));
+                    }
+                }
+                Action::Undo => println!("Moves cannot be taken back in a network game"),
+                Action::Quit => return Ok(Action::Resign),
+                action => return Ok(action)
+            }
+        }
+    }
+
+    fn answer_draw_offer(&mut self, state: &GameState) -> Action {
+        self.ui.draw_board(&state.board);
+        self.human.answer_draw_offer(state)
+    }
+}
+
+// The color the person on this side does not play: a host asking for its moves
+// is wrong, the answer is a resignation
+struct PlayerOtherColor {
+    color: Color
+}
+
+impl Player for PlayerOtherColor {
+    fn get_color(&self) -> Color {
+        self.color
+    }
+    fn get_name(&self) -> String {
+        format!("{:?}", self.color)
+    }
+
+    fn play_turn(&mut self, _state: &GameState, _legal_moves: &[Box<dyn Movement>], _time_left: Option<Duration>) -> Result<Action, String> {
+        Err(format!("The host asks for the moves of both colors, {:?} is not played here", self.color))
+    }
+}
+
+// Plays the game of the host connected to stream, with the commands read from input
+pub fn run_client(stream: TcpStream, name: &str, input: Box<dyn BufRead>) -> Result<(), String> {
+    let _ = stream.set_nodelay(true);
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+    let mut writer = stream;
+    let mut input = Some(input);
+    let mut new_player = |color: Color| -> Box<dyn Player> {
+        // The host only asks for the moves of one color
+        match input.take() {
+            Some(input) => Box::new(PlayerRemoteConsole {
+                human: PlayerHumanConsole::with_input(name, color, input),
+                ui: CheckersUiText::new(),
+                hint_engine: HintEngine::new()
+            }),
+            None => Box::new(PlayerOtherColor { color })
+        }
+    };
+    println!("Connected, waiting for the host...");
+    serve_engine(name, &mut reader, &mut writer, &mut new_player)?;
+    println!("The host has closed the game");
+    Ok(())
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::net::TcpListener;
+    use std::rc::Rc;
+    use std::thread;
+    use sm_checkers_base::GameResult;
+    use crate::checkers_game::CheckersGame;
+    use crate::game_runner::play_game;
+
+    #[test]
+    fn test_network_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The remote person asks for a hint, tries to undo, types an illegal move, then resigns
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            run_client(stream, "GUEST", Box::new(Cursor::new("hint\nundo\n1-5\nresign\n".to_string())))
+        });
+
+        let remote = PlayerNetwork::accept("REMOTE", Color::Red, &listener).unwrap();
+        assert_eq!(remote.get_remote_name(), "GUEST");
+        let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", Color::Black)));
+        random.borrow_mut().set_verbose(false);
+        let players: Vec<Rc<RefCell<dyn Player>>> = vec![random, Rc::new(RefCell::new(remote))];
+        let record = play_game(&mut CheckersGame::new(), &players, 10, 1);
+        assert_eq!(record.result, GameResult::Win(Color::Black));
+        assert_eq!(record.moves.len(), 1);
+
+        // The client stops with the game
+        drop(players);
+        assert_eq!(client.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_host_asking_both_colors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            run_client(stream, "GUEST", Box::new(Cursor::new("11-15\n".to_string())))
+        });
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut read_line = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line.trim().to_string()
+        };
+        writeln!(stream, "sm-checkers 1").unwrap();
+        assert_eq!(read_line(), "name GUEST");
+        writeln!(stream, "go B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12 0 none").unwrap();
+        assert_eq!(read_line(), "move 11-15");
+        // The client does not crash, it resigns the other color
+        writeln!(stream, "go W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,15 1 none").unwrap();
+        assert!(read_line().starts_with("info "));
+        assert_eq!(read_line(), "resign");
+        writeln!(stream, "quit").unwrap();
+        assert_eq!(client.join().unwrap(), Ok(()));
+    }
+}
@@ -0,0 +1,390 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sm_checkers_base::Color;
+use sm_checkers_base::CheckersBoard;
+use sm_checkers_base::CheckersRules;
+use sm_checkers_base::checkers_board::GameBoardObserver;
+use sm_checkers_base::GameResult;
+use sm_checkers_base::player_colors::opposite_color;
+use sm_checkers_players::*;
+use sm_checkers_players::player_bot_ai::LearningRule;
+use sm_checkers_players::neural_evaluator::MlpEvaluator;
+use sm_checkers_players::td_lambda::TdLambdaLearner;
+use sm_checkers_players::player_actions::ActionMove;
+
+use crate::checkers_game::CheckersGame;
+use crate::checkers_game::Subject;
+use crate::game_runner::play_game;
+
+// Headless training of PlayerBotAI: no board is printed, only the periodic reports.
+// Every random choice derives from the seed, so a run can be reproduced:
+// each game is played with its own seed, see play_game.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrainingOpponent {
+    SelfPlay,   // A copy of the learner, with the weights of the learner at the start of each game
+    Random      // PlayerBotRandom
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrainingModel {
+    Linear,             // The weighted features of PlayerBotAI
+    Mlp(Vec<usize>)     // An MlpEvaluator with these hidden layers, played by PlayerBotSearch
+}
+
+pub struct TrainingOptions {
+    pub model: TrainingModel,
+    pub games: usize,
+    pub opponent: TrainingOpponent,
+    pub seed: u64,
+    pub report_every: usize,
+    pub eval_games: usize,      // Number of games against PlayerBotRandom for each report
+    pub max_turns: usize,       // A game is a draw after that many turns
+    pub load: Option<String>,   // Weights file to start from
+    pub save: Option<String>,   // Weights file written at the end of the training
+    pub learning_rule: Option<LearningRule>,    // None: the rule of the loaded file, or LMS
+    pub eta: Option<f64>        // Learning rate, None: the rate of the loaded file, or the default one
+}
+
+impl TrainingOptions {
+    pub fn new() -> TrainingOptions {
+        TrainingOptions {
+            model: TrainingModel::Linear,
+            games: 100,
+            opponent: TrainingOpponent::SelfPlay,
+            seed: 0,
+            report_every: 10,
+            eval_games: 20,
+            max_turns: 200,
+            load: None,
+            save: None,
+            learning_rule: None,
+            eta: None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize
+}
+
+impl Score {
+    pub fn add(&mut self, result: &GameResult, color: Color) {
+        match result {
+            GameResult::Win(winner) if *winner == color => self.wins += 1,
+            GameResult::Win(_) => self.losses += 1,
+            GameResult::Draw => self.draws += 1
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn percent_string(&self) -> String {
+        let games = self.games().max(1) as f64;
+        format!("win {:.1}% draw {:.1}% loss {:.1}%",
+            100.0 * self.wins as f64 / games, 100.0 * self.draws as f64 / games, 100.0 * self.losses as f64 / games)
+    }
+}
+
+// Different seeds for the different random streams of a run
+pub fn derive_seed(seed: u64, stream: u64, index: usize) -> u64 {
+    seed ^ (stream << 48) ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+// The black player comes first
+fn order_players(a: Rc<RefCell<dyn Player>>, b: Rc<RefCell<dyn Player>>) -> Vec<Rc<RefCell<dyn Player>>> {
+    if a.borrow().get_color() == Color::Black {
+        vec![a, b]
+    }
+    else {
+        vec![b, a]
+    }
+}
+
+pub fn run_training(options: &TrainingOptions) -> Result<Vec<f64>, String> {
+    println!("Training PlayerBotAI: {} games against {:?}, seed: {}", options.games, options.opponent, options.seed);
+
+    let learner = match &options.load {
+        Some(path) => {
+            let player = PlayerBotAI::from_weights_file("LEARNER", Color::Black, path)?;
+            println!("Starting from {}, already trained with {} games", path, player.get_learning_stats().games);
+            Rc::new(RefCell::new(player))
+        }
+        None => Rc::new(RefCell::new(PlayerBotAI::new("LEARNER", Color::Black)))
+    };
+    if let Some(rule) = options.learning_rule {
+        learner.borrow_mut().set_learning_rule(rule);
+    }
+    if let Some(eta) = options.eta {
+        learner.borrow_mut().set_eta(eta);
+    }
+    learner.borrow_mut().set_verbose(false);
+
+    let mut training_score = Score::default();
+    for game_index in 0..options.games {
+        // The learner alternates colors, to learn both sides of the board
+        let learner_color = if game_index % 2 == 0 { Color::Black } else { Color::Red };
+        let opponent_color = opposite_color(learner_color);
+        learner.borrow_mut().set_color(learner_color);
+        learner.borrow_mut().new_game();
+
+        let mut game = CheckersGame::new();
+        let opponent: Rc<RefCell<dyn Player>> = match options.opponent {
+            TrainingOpponent::SelfPlay => {
+                let mirror = Rc::new(RefCell::new(PlayerBotAI::new("MIRROR", opponent_color)));
+                mirror.borrow_mut().set_weights(learner.borrow().get_evaluator().get_weights());
+                mirror.borrow_mut().set_verbose(false);
+                mirror
+            }
+            TrainingOpponent::Random => {
+                let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", opponent_color)));
+                random.borrow_mut().set_verbose(false);
+                random
+            }
+        };
+
+        let players = order_players(learner.clone(), opponent);
+        let result = play_game(&mut game, &players, options.max_turns, derive_seed(options.seed, 2, game_index)).result;
+        training_score.add(&result, learner_color);
+        learner.borrow_mut().learn_from_game(&result);
+
+        if (game_index + 1) % options.report_every.max(1) == 0 || game_index + 1 == options.games {
+            let weights = learner.borrow().get_evaluator().get_weights().to_vec();
+            let eval_score = evaluate_against_random(&weights, options.eval_games, derive_seed(options.seed, 3, game_index), options.max_turns);
+            println!("games: {:>6} | training: {} | vs random: {} | last mse: {:.3}",
+                game_index + 1,
+                training_score.percent_string(),
+                eval_score.percent_string(),
+                learner.borrow().get_learning_stats().mean_squared_error);
+            training_score = Score::default();
+        }
+    }
+
+    let weights = learner.borrow().get_evaluator().get_weights().to_vec();
+    println!("Final weights: {:?}", weights);
+    if let Some(path) = &options.save {
+        learner.borrow().save_weights_file(path)?;
+        println!("Weights saved to {}", path);
+    }
+    Ok(weights)
+}
+
+// Search depth of the MLP players during the training: the network is evaluated at every leaf
+const MLP_TRAINING_DEPTH: u32 = 2;
+// Random plies at the start of each self-play game, the search player being deterministic
+const MLP_RANDOM_OPENING_PLIES: usize = 4;
+
+// Keeps every board of a game
+struct PositionRecorder {
+    positions: Vec<CheckersBoard>
+}
+
+impl GameBoardObserver for PositionRecorder {
+    fn update(&mut self, board: &CheckersBoard) {
+        self.positions.push(board.clone());
+    }
+}
+
+fn new_mlp_player(name: &str, color: Color, mlp: &MlpEvaluator) -> Rc<RefCell<PlayerBotSearch>> {
+    let player = Rc::new(RefCell::new(PlayerBotSearch::new(name, color)));
+    player.borrow_mut().set_evaluator(Arc::new(mlp.clone()));
+    player.borrow_mut().set_max_depth(MLP_TRAINING_DEPTH);
+    // Depth limited only, so that the games do not depend on the speed of the machine
+    player.borrow_mut().set_max_time(Duration::from_secs(3600));
+    player.borrow_mut().set_verbose(false);
+    player
+}
+
+// Self-play training of an MlpEvaluator with TD(lambda): both sides learn from every game.
+// Returns the trained network.
+pub fn run_mlp_training(options: &TrainingOptions) -> Result<MlpEvaluator, String> {
+    let hidden_layers = match &options.model {
+        TrainingModel::Mlp(hidden_layers) => hidden_layers.clone(),
+        TrainingModel::Linear => return Err("run_mlp_training expects the MLP model".into())
+    };
+    let lambda = match options.learning_rule {
+        None => 0.7,
+        Some(LearningRule::TdLambda(lambda)) => lambda,
+        Some(LearningRule::Lms) => return Err("The MLP is trained with TD(lambda) only".into())
+    };
+    let mut mlp = match &options.load {
+        Some(path) => MlpEvaluator::load(path)?,
+        None => MlpEvaluator::new(&hidden_layers, derive_seed(options.seed, 1, 0))
+    };
+    let learner = TdLambdaLearner::new(lambda, options.eta.unwrap_or(0.1));
+    println!("Training MlpEvaluator {:?}: {} games against {:?}, seed: {}",
+        mlp.get_layer_sizes(), options.games, options.opponent, options.seed);
+
+    for game_index in 0..options.games {
+        let learner_color = if game_index % 2 == 0 { Color::Black } else { Color::Red };
+        let opponent_color = opposite_color(learner_color);
+
+        let mut game = CheckersGame::new();
+        let recorder = Rc::new(RefCell::new(PositionRecorder { positions: vec![] }));
+        game.register_observer(recorder.clone());
+        let player = new_mlp_player("LEARNER", learner_color, &mlp);
+        let opponent: Rc<RefCell<dyn Player>> = match options.opponent {
+            TrainingOpponent::SelfPlay => new_mlp_player("MIRROR", opponent_color, &mlp),
+            TrainingOpponent::Random => {
+                let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", opponent_color)));
+                random.borrow_mut().set_verbose(false);
+                random
+            }
+        };
+
+        // Random opening, for different games
+        let mut opening_plies = 0;
+        while opening_plies < MLP_RANDOM_OPENING_PLIES {
+            let color = if opening_plies % 2 == 0 { Color::Black } else { Color::Red };
+            let board = recorder.borrow().positions.last().cloned().unwrap_or_else(CheckersBoard::new);
+            let movements = CheckersRules::get_legal_movements(&board, color);
+            if movements.is_empty() {
+                break;
+            }
+            let choice = derive_seed(options.seed, 8, game_index * MLP_RANDOM_OPENING_PLIES + opening_plies) as usize % movements.len();
+            game.move_piece(&ActionMove::new(color, &movements[choice].tiles()))?;
+            opening_plies += 1;
+        }
+        if opening_plies < MLP_RANDOM_OPENING_PLIES {
+            continue;
+        }
+
+        let result = play_game(&mut game, &order_players(player, opponent), options.max_turns, derive_seed(options.seed, 2, game_index)).result;
+        let positions = std::mem::take(&mut recorder.borrow_mut().positions);
+        let mut mean_squared_error = learner.learn_from_game(&mut mlp, &positions, learner_color, &result);
+        if options.opponent == TrainingOpponent::SelfPlay {
+            mean_squared_error = (mean_squared_error + learner.learn_from_game(&mut mlp, &positions, opponent_color, &result)) / 2.0;
+        }
+
+        if (game_index + 1) % options.report_every.max(1) == 0 || game_index + 1 == options.games {
+            let eval_score = evaluate_mlp_against_random(&mlp, options.eval_games, derive_seed(options.seed, 3, game_index), options.max_turns);
+            println!("games: {:>6} | vs random: {} | last mse: {:.3}",
+                game_index + 1,
+                eval_score.percent_string(),
+                mean_squared_error);
+        }
+    }
+
+    if let Some(path) = &options.save {
+        mlp.save(path)?;
+        println!("Network saved to {}", path);
+    }
+    Ok(mlp)
+}
+
+// Plays the network, with a shallow search, against PlayerBotRandom, alternating colors
+pub fn evaluate_mlp_against_random(mlp: &MlpEvaluator, nb_games: usize, seed: u64, max_turns: usize) -> Score {
+    let mut score = Score::default();
+    for game_index in 0..nb_games {
+        let mlp_color = if game_index % 2 == 0 { Color::Black } else { Color::Red };
+        let player = new_mlp_player("EVALUATED", mlp_color, mlp);
+        let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", opposite_color(mlp_color))));
+        random.borrow_mut().set_verbose(false);
+
+        let mut game = CheckersGame::new();
+        let result = play_game(&mut game, &order_players(player, random), max_turns, derive_seed(seed, 5, game_index)).result;
+        score.add(&result, mlp_color);
+    }
+    score
+}
+
+// Plays the weights of a against the weights of b, alternating colors.
+// Returns the score of a.
+pub fn compare_weights(weights_a: &[f64], weights_b: &[f64], nb_games: usize, seed: u64, max_turns: usize) -> Score {
+    let mut score = Score::default();
+    for game_index in 0..nb_games {
+        let a_color = if game_index % 2 == 0 { Color::Black } else { Color::Red };
+        let a = Rc::new(RefCell::new(PlayerBotAI::new("A", a_color)));
+        a.borrow_mut().set_weights(weights_a);
+        a.borrow_mut().set_verbose(false);
+        let b = Rc::new(RefCell::new(PlayerBotAI::new("B", opposite_color(a_color))));
+        b.borrow_mut().set_weights(weights_b);
+        b.borrow_mut().set_verbose(false);
+
+        let mut game = CheckersGame::new();
+        let result = play_game(&mut game, &order_players(a, b), max_turns, derive_seed(seed, 6, game_index)).result;
+        score.add(&result, a_color);
+    }
+    score
+}
+
+// Plays the weights against PlayerBotRandom, without learning, alternating colors
+pub fn evaluate_against_random(weights: &[f64], nb_games: usize, seed: u64, max_turns: usize) -> Score {
+    let mut score = Score::default();
+    for game_index in 0..nb_games {
+        let ai_color = if game_index % 2 == 0 { Color::Black } else { Color::Red };
+        let ai = Rc::new(RefCell::new(PlayerBotAI::new("EVALUATED", ai_color)));
+        ai.borrow_mut().set_weights(weights);
+        ai.borrow_mut().set_verbose(false);
+        let random = Rc::new(RefCell::new(PlayerBotRandom::new("RANDOM", opposite_color(ai_color))));
+        random.borrow_mut().set_verbose(false);
+
+        let mut game = CheckersGame::new();
+        let result = play_game(&mut game, &order_players(ai, random), max_turns, derive_seed(seed, 4, game_index)).result;
+        score.add(&result, ai_color);
+    }
+    score
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+/// Unit tests
+///
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training_is_reproducible() {
+        let mut options = TrainingOptions::new();
+        options.games = 4;
+        options.report_every = 2;
+        options.eval_games = 2;
+        options.max_turns = 60;
+        options.seed = 1234;
+
+        let weights_1 = run_training(&options).unwrap();
+        let weights_2 = run_training(&options).unwrap();
+        assert_eq!(weights_1, weights_2);
+
+        options.opponent = TrainingOpponent::Random;
+        let weights_3 = run_training(&options).unwrap();
+        assert_ne!(weights_1, weights_3);
+
+        options.opponent = TrainingOpponent::SelfPlay;
+        options.learning_rule = Some(LearningRule::TdLambda(0.7));
+        let weights_4 = run_training(&options).unwrap();
+        assert_ne!(weights_1, weights_4);
+        assert_eq!(weights_4, run_training(&options).unwrap());
+
+        // Same weights: the score is even when the seeds are
+        let score = compare_weights(&weights_1, &weights_1, 4, 1, 60);
+        assert_eq!(score.games(), 4);
+    }
+
+    #[test]
+    fn test_mlp_training_is_reproducible() {
+        let mut options = TrainingOptions::new();
+        options.model = TrainingModel::Mlp(vec![8]);
+        options.games = 2;
+        options.report_every = 2;
+        options.eval_games = 1;
+        options.max_turns = 40;
+        options.seed = 99;
+
+        let mlp_1 = run_mlp_training(&options).unwrap();
+        assert_eq!(mlp_1, run_mlp_training(&options).unwrap());
+        assert_ne!(mlp_1, MlpEvaluator::new(&[8], derive_seed(99, 1, 0)));
+
+        options.learning_rule = Some(LearningRule::Lms);
+        assert!(run_mlp_training(&options).is_err());
+    }
+}
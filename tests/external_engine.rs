@@ -0,0 +1,51 @@
+// End to end: the reference engine ("sm-checkers engine") plays through PlayerExternal
+
+use std::time::Duration;
+use sm_checkers_base::{CheckersBoard, CheckersRules, Color};
+use sm_checkers_base::player_colors::opposite_color;
+use sm_checkers_players::*;
+use sm_checkers_players::player_trait::GameState;
+
+fn start_engine(color: Color) -> PlayerExternal {
+    let args: Vec<String> = ["engine", "--depth", "3", "--time", "100"].iter().map(|a| a.to_string()).collect();
+    PlayerExternal::new("ENGINE", color, env!("CARGO_BIN_EXE_sm-checkers"), &args).unwrap()
+}
+
+#[test]
+fn test_engine_plays_a_game() {
+    let mut engine = start_engine(Color::Black);
+    assert_eq!(engine.get_engine_name(), "sm-checkers search");
+    let mut random = PlayerBotRandom::new("RANDOM", Color::Red);
+    random.set_verbose(false);
+    engine.set_seed(1);
+    random.set_seed(2);
+
+    let mut state = GameState { board: CheckersBoard::new(), next_color: Color::Black, turn: 0, draw_offer: None };
+    let winner = loop {
+        let legal_moves = CheckersRules::get_legal_movements(&state.board, state.next_color);
+        if legal_moves.is_empty() {
+            break Some(opposite_color(state.next_color));
+        }
+        if state.turn >= 200 {
+            break None;
+        }
+        let player: &mut dyn Player = if state.next_color == Color::Black { &mut engine } else { &mut random };
+        let action = player.play_turn(&state, &legal_moves, Some(Duration::from_secs(2))).unwrap();
+        let movement = match action {
+            Action::Move(action_move) => action_move.to_movement(),
+            action => panic!("Unexpected action {:?}", action)
+        };
+        // Only legal moves are returned
+        assert!(legal_moves.iter().any(|m| m.tiles() == movement.tiles()));
+        state.board.move_piece(&movement).unwrap();
+        state.next_color = opposite_color(state.next_color);
+        state.turn += 1;
+    };
+    // The search engine does not lose against random moves
+    assert_ne!(winner, Some(Color::Red));
+
+    // The engine answers for both colors
+    let mut red_engine = start_engine(Color::Red);
+    let state = GameState { board: CheckersBoard::new(), next_color: Color::Red, turn: 1, draw_offer: Some(Color::Black) };
+    assert_eq!(red_engine.answer_draw_offer(&state), Action::DeclineDraw);
+}